@@ -0,0 +1,98 @@
+//! Compares the throughput of `FastIdWorker::next_id` under contention with
+//! the previous implementation guarded by two mutexes.
+//!
+//! Run with `cargo bench --bench next_id`.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+use fastid::{FastIdWorker, IdLayout};
+
+const IDS_PER_THREAD: usize = 200_000;
+
+/// The mutex based `next_id` as it was before the state was packed into a
+/// single atomic.
+struct MutexWorker {
+    layout: IdLayout,
+    machine_id: u64,
+    sequence: Mutex<u64>,
+    last_timestamp: Mutex<u64>,
+}
+
+impl MutexWorker {
+    fn new(layout: IdLayout, machine_id: u64) -> Self {
+        MutexWorker {
+            layout,
+            machine_id,
+            sequence: Mutex::new(0),
+            last_timestamp: Mutex::new(0),
+        }
+    }
+
+    fn next_id(&self) -> i64 {
+        loop {
+            let ts = self.layout.ticks_at(SystemTime::now());
+
+            let mut last_timestamp = self.last_timestamp.lock().unwrap();
+            let mut sequence = self.sequence.lock().unwrap();
+
+            if ts > *last_timestamp {
+                *last_timestamp = ts;
+                *sequence = 0
+            } else if *sequence >= self.layout.max_sequence() {
+                continue;
+            } else {
+                *sequence += 1;
+            }
+
+            let machine_bits = self.layout.machine_bits();
+            let sequence_bits = self.layout.sequence_bits();
+
+            let id = ((ts & self.layout.max_ticks()) << (machine_bits + sequence_bits))
+                | (*sequence << machine_bits)
+                | self.machine_id;
+
+            return id as i64;
+        }
+    }
+}
+
+fn run(name: &str, threads: usize, next_id: &(dyn Fn() -> i64 + Sync)) {
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for _ in 0..IDS_PER_THREAD {
+                    std::hint::black_box(next_id());
+                }
+            });
+        }
+    });
+
+    let elapsed = start.elapsed();
+    let total = threads * IDS_PER_THREAD;
+
+    println!(
+        "{:<8} {:>2} threads: {:>10.0} ids/s ({:?})",
+        name,
+        threads,
+        total as f64 / elapsed.as_secs_f64(),
+        elapsed
+    );
+}
+
+fn main() {
+    // the default layout mints at most 128 ids per ~1ms tick, which would
+    // make both workers wait on the clock rather than on each other
+    let layout = IdLayout::new(40, 4, 19).unwrap();
+
+    for threads in [1, 2, 4, 8] {
+        let atomic = FastIdWorker::with_layout(layout, 1);
+        run("atomic", threads, &|| atomic.next_id().as_i64());
+
+        let mutex = MutexWorker::new(layout, 1);
+        run("mutex", threads, &|| mutex.next_id());
+    }
+}
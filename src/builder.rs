@@ -0,0 +1,255 @@
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "guid")]
+use crate::GuidFormat;
+use crate::{
+    Clock, ClockRegression, ExhaustionWarning, FastIdError, FastIdWorker, IdLayout,
+    SequenceExhaustion, ShardedFastIdWorker, SystemClock,
+};
+
+/// Builds a [`FastIdWorker`], validating its configuration up front.
+///
+/// ```
+/// use fastid::{FastIdWorker, IdLayout};
+///
+/// let worker = FastIdWorker::builder()
+///     .layout(IdLayout::new(41, 10, 12).unwrap())
+///     .machine_id(7)
+///     .build()
+///     .unwrap();
+///
+/// let id = worker.next_id();
+/// assert_eq!(worker.decode(&id).machine_id, 7);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FastIdWorkerBuilder<C = SystemClock> {
+    clock: C,
+    layout: IdLayout,
+    machine_id: u64,
+    clock_regression: ClockRegression,
+    exhaustion: SequenceExhaustion,
+    exhaustion_warning: Option<(Duration, fn(SystemTime))>,
+    /// Only validated against the layout when set explicitly, so enabling
+    /// the `guid` feature does not change which workers can be built.
+    #[cfg(feature = "guid")]
+    guid_format: Option<GuidFormat>,
+}
+
+impl FastIdWorkerBuilder {
+    pub fn new() -> Self {
+        FastIdWorkerBuilder {
+            clock: SystemClock,
+            layout: IdLayout::default(),
+            machine_id: 0,
+            clock_regression: ClockRegression::default(),
+            exhaustion: SequenceExhaustion::default(),
+            exhaustion_warning: None,
+            #[cfg(feature = "guid")]
+            guid_format: None,
+        }
+    }
+}
+
+impl Default for FastIdWorkerBuilder {
+    fn default() -> Self {
+        FastIdWorkerBuilder::new()
+    }
+}
+
+impl<C: Clock> FastIdWorkerBuilder<C> {
+    /// Sets the clock the worker reads the time from, [`SystemClock`] if unset.
+    pub fn clock<D: Clock>(self, clock: D) -> FastIdWorkerBuilder<D> {
+        FastIdWorkerBuilder {
+            clock,
+            layout: self.layout,
+            machine_id: self.machine_id,
+            clock_regression: self.clock_regression,
+            exhaustion: self.exhaustion,
+            exhaustion_warning: self.exhaustion_warning,
+            #[cfg(feature = "guid")]
+            guid_format: self.guid_format,
+        }
+    }
+
+    /// Sets the layout of the generated ids, [`IdLayout::default`] if unset.
+    pub fn layout(mut self, layout: IdLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets the machine id embedded in every generated id, 0 if unset.
+    pub fn machine_id(mut self, machine_id: u64) -> Self {
+        self.machine_id = machine_id;
+        self
+    }
+
+    /// Sets what to do when the clock moves backwards,
+    /// [`ClockRegression::Logical`] if unset.
+    pub fn clock_regression(mut self, clock_regression: ClockRegression) -> Self {
+        self.clock_regression = clock_regression;
+        self
+    }
+
+    /// Sets what to do when the sequence of a tick is exhausted,
+    /// [`SequenceExhaustion::Sleep`] if unset.
+    pub fn sequence_exhaustion(mut self, exhaustion: SequenceExhaustion) -> Self {
+        self.exhaustion = exhaustion;
+        self
+    }
+
+    /// Calls `callback` with the instant the time field runs out the first
+    /// time an id is generated within `horizon` of it.
+    pub fn exhaustion_warning(mut self, horizon: Duration, callback: fn(SystemTime)) -> Self {
+        self.exhaustion_warning = Some((horizon, callback));
+        self
+    }
+
+    /// Sets the format of the GUIDs returned by [`FastIdWorker::to_guid`],
+    /// [`GuidFormat::FastId`] if unset.
+    ///
+    /// Once set, [`build`](FastIdWorkerBuilder::build) fails if the layout
+    /// cannot produce unique GUIDs of the format. Otherwise only
+    /// [`FastIdWorker::to_guid`] does.
+    #[cfg(feature = "guid")]
+    pub fn guid_format(mut self, guid_format: GuidFormat) -> Self {
+        self.guid_format = Some(guid_format);
+        self
+    }
+
+    pub fn build(self) -> Result<FastIdWorker<C>, FastIdError> {
+        self.validate()?;
+
+        Ok(FastIdWorker {
+            clock: self.clock,
+            layout: self.layout,
+            clock_regression: self.clock_regression,
+            exhaustion: self.exhaustion,
+            #[cfg(feature = "guid")]
+            guid_format: self.guid_format.unwrap_or_default(),
+
+            machine_id: self.machine_id,
+
+            state: AtomicU64::new(0),
+
+            exhaustion_warning: self.exhaustion_warning.map(|(horizon, callback)| {
+                let horizon = horizon.as_nanos() / self.layout.tick().as_nanos() as u128;
+                let last = self.layout.max_ticks() as u128;
+
+                ExhaustionWarning {
+                    from: last.saturating_sub(horizon) as u64,
+                    callback,
+                    warned: AtomicBool::new(false),
+                }
+            }),
+        })
+    }
+
+    /// Builds a generator splitting the sequence field into `2^shard_bits`
+    /// shards, see [`ShardedFastIdWorker`].
+    pub fn build_sharded(self, shard_bits: usize) -> Result<ShardedFastIdWorker<C>, FastIdError>
+    where
+        C: Clone,
+    {
+        let layout = self.layout;
+
+        if shard_bits > layout.sequence_bits() {
+            return Err(FastIdError::TooManyShards {
+                bits: shard_bits,
+                max: layout.sequence_bits(),
+            });
+        }
+
+        // the workers of the shards have fewer sequence bits, so they are
+        // valid whenever the whole layout is
+        self.validate()?;
+
+        let builder = self.layout(layout.without_sequence_bits(shard_bits));
+
+        Ok(ShardedFastIdWorker::new(builder, layout, shard_bits))
+    }
+
+    fn validate(&self) -> Result<(), FastIdError> {
+        #[cfg(feature = "guid")]
+        if let Some(guid_format) = self.guid_format {
+            guid_format.validate(&self.layout, self.machine_id)?;
+        }
+
+        let max = self.layout.max_machine_id();
+        if self.machine_id > max {
+            return Err(FastIdError::MachineIdTooLarge {
+                machine_id: self.machine_id,
+                max,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_machine_id_out_of_range() {
+        let layout = IdLayout::new(40, 4, 7).unwrap();
+
+        let result = FastIdWorker::builder()
+            .layout(layout)
+            .machine_id(16)
+            .build();
+        assert_eq!(
+            result.err(),
+            Some(FastIdError::MachineIdTooLarge {
+                machine_id: 16,
+                max: 15
+            })
+        );
+
+        let result = FastIdWorker::builder()
+            .layout(layout)
+            .machine_id(15)
+            .build();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn accepts_empty_machine_field() {
+        let layout = IdLayout::new(49, 0, 14).unwrap();
+
+        let worker = FastIdWorker::builder().layout(layout).build();
+        assert!(worker.is_ok());
+
+        let worker = FastIdWorker::builder().layout(layout).machine_id(1).build();
+        assert_eq!(
+            worker.err(),
+            Some(FastIdError::MachineIdTooLarge {
+                machine_id: 1,
+                max: 0
+            })
+        );
+    }
+
+    #[cfg(feature = "guid")]
+    #[test]
+    fn rejects_sequence_wider_than_guid_clock_sequence() {
+        let layout = IdLayout::new(40, 8, 15).unwrap();
+
+        let result = FastIdWorker::builder()
+            .layout(layout)
+            .guid_format(GuidFormat::FastId)
+            .build();
+        assert_eq!(
+            result.err(),
+            Some(FastIdError::SequenceTooWide { bits: 15, max: 14 })
+        );
+
+        // without a format the worker builds, and only GUIDs fail
+        let worker = FastIdWorker::builder().layout(layout).build().unwrap();
+        assert_eq!(
+            worker.to_guid(&worker.next_id()),
+            Err(FastIdError::SequenceTooWide { bits: 15, max: 14 })
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Keyed permutations of ids, to publish ids that leak nothing.
+
+use std::fmt;
+
+use crate::FastId;
+
+const ROUNDS: usize = 12;
+
+/// The fractional part of the golden ratio, spreading the round keys apart.
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Maps ids to opaque ids and back with a secret key, so published ids do not
+/// reveal when, where or how fast they were generated.
+///
+/// The cipher is a 12-round Feistel network over 64 bits, repeated until the
+/// result fits in the 63 bits of an id. Encrypted ids are ordinary
+/// [`FastId`]s that work with every string encoding, but no longer sort by
+/// time. It hides the fields of ids from the public, it is not a vetted block
+/// cipher for protecting secrets.
+///
+/// ```
+/// use fastid::{FastId, FastIdCipher, FastIdWorker};
+///
+/// let cipher = FastIdCipher::new(*b"0123456789abcdef");
+/// let id = FastIdWorker::new(1).next_id();
+///
+/// let public = cipher.encrypt(id).to_base32();
+/// let parsed = FastId::from_base32(&public).unwrap();
+/// assert_eq!(cipher.decrypt(parsed), id);
+/// ```
+#[derive(Clone)]
+pub struct FastIdCipher {
+    round_keys: [u64; ROUNDS],
+}
+
+impl FastIdCipher {
+    pub fn new(key: [u8; 16]) -> Self {
+        let key = u128::from_le_bytes(key);
+        let (low, high) = (key as u64, (key >> 64) as u64);
+
+        let mut round_keys = [0; ROUNDS];
+        for (i, round_key) in round_keys.iter_mut().enumerate() {
+            let counter = GOLDEN_GAMMA.wrapping_mul(i as u64 + 1);
+            *round_key = mix(low ^ mix(high.wrapping_add(counter)));
+        }
+
+        FastIdCipher { round_keys }
+    }
+
+    /// Returns the opaque id published in place of `id`.
+    pub fn encrypt(&self, id: FastId) -> FastId {
+        // walks the cycle of the 64-bit permutation back into 63 bits
+        let mut block = id.as_u64();
+        loop {
+            block = self.permute(block);
+            if let Ok(id) = FastId::try_from(block) {
+                return id;
+            }
+        }
+    }
+
+    /// Returns the id an opaque id returned by [`FastIdCipher::encrypt`] was
+    /// built from.
+    pub fn decrypt(&self, id: FastId) -> FastId {
+        let mut block = id.as_u64();
+        loop {
+            block = self.invert(block);
+            if let Ok(id) = FastId::try_from(block) {
+                return id;
+            }
+        }
+    }
+
+    fn permute(&self, block: u64) -> u64 {
+        let (mut left, mut right) = ((block >> 32) as u32, block as u32);
+
+        for &round_key in &self.round_keys {
+            (left, right) = (right, left ^ round(right, round_key));
+        }
+
+        ((left as u64) << 32) | right as u64
+    }
+
+    fn invert(&self, block: u64) -> u64 {
+        let (mut left, mut right) = ((block >> 32) as u32, block as u32);
+
+        for &round_key in self.round_keys.iter().rev() {
+            (left, right) = (right ^ round(left, round_key), left);
+        }
+
+        ((left as u64) << 32) | right as u64
+    }
+}
+
+/// Hides the round keys, which are as secret as the key itself.
+impl fmt::Debug for FastIdCipher {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("FastIdCipher").finish_non_exhaustive()
+    }
+}
+
+fn round(half: u32, round_key: u64) -> u32 {
+    (mix(half as u64 ^ round_key) >> 32) as u32
+}
+
+/// The finalizer of SplitMix64, spreading every input bit over the output.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastIdWorker;
+
+    const KEY: [u8; 16] = *b"0123456789abcdef";
+
+    #[test]
+    fn can_round_trip_ids() {
+        let cipher = FastIdCipher::new(KEY);
+        let worker = FastIdWorker::new(1);
+
+        let mut ids: Vec<FastId> = (0..1000).map(|_| worker.next_id()).collect();
+        ids.push(FastId::try_from(0i64).unwrap());
+        ids.push(FastId::try_from(i64::MAX).unwrap());
+
+        for id in ids {
+            let encrypted = cipher.encrypt(id);
+
+            assert_ne!(encrypted, id);
+            assert_eq!(cipher.decrypt(encrypted), id);
+        }
+    }
+
+    #[test]
+    fn hides_the_order_of_ids() {
+        let cipher = FastIdCipher::new(KEY);
+        let worker = FastIdWorker::new(1);
+
+        let ids: Vec<FastId> = (0..100).map(|_| worker.next_id()).collect();
+        let encrypted: Vec<FastId> = ids.iter().map(|&id| cipher.encrypt(id)).collect();
+
+        assert!(encrypted.windows(2).any(|w| w[0] > w[1]));
+        assert!(encrypted.windows(2).any(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn depends_on_every_key_byte() {
+        let id = FastId::try_from(1_234_567_890_123_456_789i64).unwrap();
+        let encrypted = FastIdCipher::new(KEY).encrypt(id);
+
+        for i in 0..KEY.len() {
+            let mut key = KEY;
+            key[i] ^= 1;
+
+            assert_ne!(FastIdCipher::new(key).encrypt(id), encrypted);
+        }
+    }
+
+    #[test]
+    fn is_stable_across_releases() {
+        let cipher = FastIdCipher::new(KEY);
+        let id = FastId::try_from(1_234_567_890_123_456_789i64).unwrap();
+
+        assert_eq!(cipher.encrypt(id).as_i64(), 5_178_955_440_698_617_004);
+    }
+}
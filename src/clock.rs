@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A source of wall-clock time for a [`FastIdWorker`](crate::FastIdWorker).
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+
+    /// Blocks for `duration`, called when the worker has to wait for the
+    /// clock to advance.
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration)
+    }
+}
+
+/// Reads the time from [`SystemTime::now`], following every adjustment made
+/// to the system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Reads the system clock once and then advances it with [`Instant`], so it
+/// never moves backwards or jumps when the system clock is adjusted.
+///
+/// It drifts from the system clock by however much the latter is adjusted
+/// after the monotonic clock was created.
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    wall: SystemTime,
+    start: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        MonotonicClock {
+            wall: SystemTime::now(),
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        MonotonicClock::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> SystemTime {
+        self.wall + self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to, for tests and simulations.
+///
+/// Clones share the same time, so a test can keep a clone to drive the clock
+/// of a worker. Sleeping advances the clock instead of blocking.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    pub fn new(now: SystemTime) -> Self {
+        let clock = MockClock::default();
+        clock.set(now);
+        clock
+    }
+
+    /// Moves the clock to `now`, which may be earlier than the current time.
+    pub fn set(&self, now: SystemTime) {
+        let nanos = now
+            .duration_since(UNIX_EPOCH)
+            .expect("mock clock cannot be set before the Unix epoch")
+            .as_nanos();
+
+        self.nanos.store(nanos as u64, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    pub fn rewind(&self, duration: Duration) {
+        self.nanos
+            .fetch_sub(duration.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monotonic_clock_starts_at_system_time() {
+        let before = SystemTime::now();
+        let clock = MonotonicClock::new();
+        let first = clock.now();
+        let second = clock.now();
+
+        assert!(first >= before);
+        assert!(second >= first);
+    }
+
+    #[test]
+    fn mock_clock_is_shared_between_clones() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let clock = MockClock::new(start);
+        let handle = clock.clone();
+
+        handle.advance(Duration::from_millis(5));
+        assert_eq!(clock.now(), start + Duration::from_millis(5));
+
+        clock.sleep(Duration::from_millis(5));
+        handle.rewind(Duration::from_millis(3));
+        assert_eq!(handle.now(), start + Duration::from_millis(7));
+    }
+}
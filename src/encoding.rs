@@ -0,0 +1,283 @@
+//! Fixed-width string encodings that sort like the ids they encode.
+
+use crate::{invalid_char, ParseFastIdError};
+
+/// The digits of a positional encoding, in ascending ASCII order so that
+/// strings of the same width compare like the numbers they encode.
+pub(crate) struct Alphabet {
+    digits: &'static [u8],
+    width: usize,
+    /// Whether strings are decoded the way people type them, see
+    /// [`crockford_digit`].
+    lenient: bool,
+}
+
+impl Alphabet {
+    /// [Crockford's base32](https://www.crockford.com/base32.html), as used
+    /// by ULIDs: 13 digits without I, L, O and U.
+    pub(crate) const CROCKFORD: Alphabet = Alphabet {
+        digits: b"0123456789ABCDEFGHJKMNPQRSTVWXYZ",
+        width: 13,
+        lenient: true,
+    };
+
+    /// Lower case Crockford's base32 over 26 digits, the suffix of a
+    /// [TypeID](https://github.com/jetify-com/typeid/tree/main/spec) holding
+    /// the id as the low bits of a 128-bit UUID.
+    pub(crate) const TYPEID: Alphabet = Alphabet {
+        digits: b"0123456789abcdefghjkmnpqrstvwxyz",
+        width: 26,
+        lenient: false,
+    };
+
+    /// Digits, then upper and lower case letters: 11 digits.
+    pub(crate) const SORTABLE_BASE62: Alphabet = Alphabet {
+        digits: b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+        width: 11,
+        lenient: false,
+    };
+
+    /// The URL-safe base64 characters reordered by ASCII: 11 digits, the
+    /// big-endian bytes of the id after two zero bits.
+    pub(crate) const SORTABLE_BASE64: Alphabet = Alphabet {
+        digits: b"-0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz",
+        width: 11,
+        lenient: false,
+    };
+
+    pub(crate) fn encode(&self, mut n: u64) -> String {
+        let radix = self.digits.len() as u64;
+
+        let mut buf = vec![self.digits[0]; self.width];
+        for digit in buf.iter_mut().rev() {
+            *digit = self.digits[(n % radix) as usize];
+            n /= radix;
+        }
+
+        String::from_utf8(buf).expect("alphabets are ASCII")
+    }
+
+    pub(crate) fn decode(&self, s: &str) -> Result<u64, ParseFastIdError> {
+        if s.len() != self.width {
+            return Err(ParseFastIdError::InvalidLength {
+                expected: self.width,
+                found: s.len(),
+            });
+        }
+
+        let radix = self.digits.len() as u64;
+
+        let mut n: u64 = 0;
+        for (index, &byte) in s.as_bytes().iter().enumerate() {
+            let digit = if self.lenient {
+                crockford_digit(byte).map(|digit| digit as usize)
+            } else {
+                self.digits.iter().position(|&d| d == byte)
+            };
+            let digit = digit.ok_or(ParseFastIdError::InvalidCharacter {
+                character: invalid_char(s, byte, index),
+                index,
+            })?;
+
+            n = n
+                .checked_mul(radix)
+                .and_then(|n| n.checked_add(digit as u64))
+                .ok_or(ParseFastIdError::Overflow)?;
+        }
+
+        Ok(n)
+    }
+}
+
+/// The symbols of Crockford's mod-37 check after the 32 digits.
+const CHECK_SYMBOLS: &[u8; 5] = b"*~$=U";
+
+/// Encodes `n` as [`Alphabet::CROCKFORD`] followed by its mod-37 check
+/// symbol, in groups of five symbols separated by hyphens if `grouped`.
+pub(crate) fn encode_checked(n: u64, grouped: bool) -> String {
+    let mut s = Alphabet::CROCKFORD.encode(n);
+    s.push(check_symbol(n) as char);
+
+    if grouped {
+        s.insert(10, '-');
+        s.insert(5, '-');
+    }
+
+    s
+}
+
+/// Decodes the output of [`encode_checked`] the way people type it: in any
+/// case, with I and L read as 1, O read as 0 and hyphens anywhere.
+pub(crate) fn decode_checked(s: &str) -> Result<u64, ParseFastIdError> {
+    let symbols: Vec<(usize, u8)> = s.bytes().enumerate().filter(|&(_, b)| b != b'-').collect();
+
+    let width = Alphabet::CROCKFORD.width + 1;
+    if symbols.len() != width {
+        return Err(ParseFastIdError::InvalidLength {
+            expected: width,
+            found: symbols.len(),
+        });
+    }
+    let (&(check_index, check), digits) = symbols.split_last().expect("width is not zero");
+
+    let invalid = |index: usize, byte: u8| ParseFastIdError::InvalidCharacter {
+        character: invalid_char(s, byte, index),
+        index,
+    };
+
+    let mut n: u64 = 0;
+    for &(index, byte) in digits {
+        let digit = crockford_digit(byte).ok_or_else(|| invalid(index, byte))?;
+
+        n = n
+            .checked_mul(32)
+            .and_then(|n| n.checked_add(digit as u64))
+            .ok_or(ParseFastIdError::Overflow)?;
+    }
+
+    let found = match crockford_digit(check) {
+        Some(digit) => Alphabet::CROCKFORD.digits[digit as usize],
+        None if CHECK_SYMBOLS.contains(&check.to_ascii_uppercase()) => check.to_ascii_uppercase(),
+        None => return Err(invalid(check_index, check)),
+    };
+    if found != check_symbol(n) {
+        return Err(ParseFastIdError::InvalidCheckSymbol {
+            expected: check_symbol(n) as char,
+            found: check as char,
+        });
+    }
+
+    Ok(n)
+}
+
+/// Returns the value of a Crockford digit, reading the letters people mistake
+/// for digits as those digits.
+fn crockford_digit(byte: u8) -> Option<u8> {
+    match byte.to_ascii_uppercase() {
+        b'O' => Some(0),
+        b'I' | b'L' => Some(1),
+        byte => Alphabet::CROCKFORD
+            .digits
+            .iter()
+            .position(|&d| d == byte)
+            .map(|digit| digit as u8),
+    }
+}
+
+fn check_symbol(n: u64) -> u8 {
+    let value = (n % 37) as usize;
+
+    match Alphabet::CROCKFORD.digits.get(value) {
+        Some(&digit) => digit,
+        None => CHECK_SYMBOLS[value - 32],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALPHABETS: [Alphabet; 4] = [
+        Alphabet::CROCKFORD,
+        Alphabet::TYPEID,
+        Alphabet::SORTABLE_BASE62,
+        Alphabet::SORTABLE_BASE64,
+    ];
+
+    #[test]
+    fn alphabets_are_sorted_and_wide_enough() {
+        for alphabet in ALPHABETS {
+            assert!(alphabet.digits.windows(2).all(|w| w[0] < w[1]));
+
+            let encoded = alphabet.encode(i64::MAX as u64);
+            assert_eq!(encoded.len(), alphabet.width);
+            assert_eq!(alphabet.decode(&encoded), Ok(i64::MAX as u64));
+        }
+    }
+
+    #[test]
+    fn strings_sort_like_numbers() {
+        let numbers = [
+            0,
+            1,
+            31,
+            32,
+            61,
+            62,
+            63,
+            64,
+            1 << 40,
+            (1 << 40) + 1,
+            u64::MAX,
+        ];
+
+        for alphabet in ALPHABETS {
+            for pair in numbers.windows(2) {
+                assert!(alphabet.encode(pair[0]) < alphabet.encode(pair[1]));
+            }
+        }
+    }
+
+    #[test]
+    fn crockford_digits_are_read_in_any_case() {
+        assert_eq!(Alphabet::CROCKFORD.decode("000000000001z"), Ok(63));
+        assert_eq!(Alphabet::CROCKFORD.decode("oOOOOOOOOOOlI"), Ok(33));
+        assert_eq!(
+            Alphabet::CROCKFORD.decode("00000000000U0"),
+            Err(ParseFastIdError::InvalidCharacter {
+                character: 'U',
+                index: 11
+            })
+        );
+
+        // TypeIDs are lower case only
+        assert!(Alphabet::TYPEID
+            .decode("0000000000000000000000001Z")
+            .is_err());
+    }
+
+    #[test]
+    fn base64_digits_follow_the_big_endian_bytes() {
+        let encoded = Alphabet::SORTABLE_BASE64.encode(0x0123_4567_89AB_CDEF);
+        assert_eq!(encoded, "-3YGLT8ewrj");
+
+        // 11 digits hold 66 bits
+        assert_eq!(
+            Alphabet::SORTABLE_BASE64.decode("zzzzzzzzzzz"),
+            Err(ParseFastIdError::Overflow)
+        );
+    }
+
+    #[test]
+    fn check_symbols_catch_typos() {
+        assert_eq!(encode_checked(0, false), "00000000000000");
+        assert_eq!(encode_checked(36, false), "0000000000014U");
+        assert_eq!(encode_checked(1234, true), "00000-00000-16JD");
+
+        assert_eq!(decode_checked("00000-00000-16jd"), Ok(1234));
+        assert_eq!(decode_checked("oOOOO0000Oi6JD"), Ok(1234));
+        assert_eq!(decode_checked("0000000000014u"), Ok(36));
+        assert_eq!(
+            decode_checked("00000-00000-17JD"),
+            Err(ParseFastIdError::InvalidCheckSymbol {
+                expected: '8',
+                found: 'D'
+            })
+        );
+        // U is only valid as a check symbol
+        assert_eq!(
+            decode_checked("000000000000U0"),
+            Err(ParseFastIdError::InvalidCharacter {
+                character: 'U',
+                index: 12
+            })
+        );
+        assert_eq!(
+            decode_checked("00000-00000-16J"),
+            Err(ParseFastIdError::InvalidLength {
+                expected: 14,
+                found: 13
+            })
+        );
+    }
+}
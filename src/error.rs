@@ -0,0 +1,150 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Errors returned when configuring or running a [`FastIdWorker`](crate::FastIdWorker).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FastIdError {
+    /// The time, machine and sequence fields together need more than the
+    /// 63 bits available below the sign bit.
+    TooManyBits { total: usize },
+    /// The sequence field is wider than the GUID format can hold.
+    ///
+    /// Only returned when the `guid` feature is enabled.
+    SequenceTooWide { bits: usize, max: usize },
+    /// The ticks are too short for the GUID format to tell them apart.
+    ///
+    /// Only returned when the `guid` feature is enabled.
+    TickTooShort { tick: Duration, min: Duration },
+    /// The machine id does not fit in the machine field and would be
+    /// truncated, colliding with the ids of another machine.
+    MachineIdTooLarge { machine_id: u64, max: u64 },
+    /// The clock reads earlier than the timestamp of the last generated id.
+    ClockMovedBackwards { by: Duration },
+    /// The sequence of the current tick is exhausted and the next id can only
+    /// be generated after `retry_after`.
+    WouldBlock { retry_after: Duration },
+    /// The clock is past the last tick the time field can hold.
+    TimeExhausted,
+    /// The sequence field is narrower than the bits needed to number the
+    /// shards of a [`ShardedFastIdWorker`](crate::ShardedFastIdWorker).
+    TooManyShards { bits: usize, max: usize },
+    /// Every shard of a [`ShardedFastIdWorker`](crate::ShardedFastIdWorker)
+    /// is claimed.
+    ShardsExhausted,
+    /// The GUID has another version than the format produces.
+    ///
+    /// Only returned when the `guid` feature is enabled.
+    GuidVersion { expected: usize, found: usize },
+    /// The GUID does not have the RFC 9562 variant.
+    ///
+    /// Only returned when the `guid` feature is enabled.
+    GuidVariant,
+    /// The GUID was not built from an id of the layout, e.g. its timestamp is
+    /// before the epoch or its machine id does not fit in the machine field.
+    ///
+    /// Only returned when the `guid` feature is enabled.
+    GuidOutOfRange,
+}
+
+impl fmt::Display for FastIdError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FastIdError::TooManyBits { total } => {
+                write!(fmt, "layout needs {} bits but only 63 are available", total)
+            }
+            FastIdError::SequenceTooWide { bits, max } => write!(
+                fmt,
+                "sequence field is {} bits wide but at most {} are supported",
+                bits, max
+            ),
+            FastIdError::TickTooShort { tick, min } => write!(
+                fmt,
+                "ticks of {:?} are shorter than the minimum of {:?}",
+                tick, min
+            ),
+            FastIdError::MachineIdTooLarge { machine_id, max } => write!(
+                fmt,
+                "machine id {} is larger than the maximum of {}",
+                machine_id, max
+            ),
+            FastIdError::ClockMovedBackwards { by } => {
+                write!(fmt, "clock moved backwards by {:?}", by)
+            }
+            FastIdError::WouldBlock { retry_after } => {
+                write!(fmt, "sequence exhausted, retry after {:?}", retry_after)
+            }
+            FastIdError::TimeExhausted => write!(fmt, "time field exhausted"),
+            FastIdError::TooManyShards { bits, max } => write!(
+                fmt,
+                "{} shard bits do not fit in a sequence field of {} bits",
+                bits, max
+            ),
+            FastIdError::ShardsExhausted => write!(fmt, "all shards are claimed"),
+            FastIdError::GuidVersion { expected, found } => write!(
+                fmt,
+                "expected a version {} GUID but found version {}",
+                expected, found
+            ),
+            FastIdError::GuidVariant => write!(fmt, "GUID is not an RFC 9562 UUID"),
+            FastIdError::GuidOutOfRange => write!(fmt, "GUID does not fit in the layout"),
+        }
+    }
+}
+
+impl std::error::Error for FastIdError {}
+
+/// Errors returned when parsing a [`FastId`](crate::FastId) from a string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseFastIdError {
+    /// The string is not as long as the encoding requires.
+    InvalidLength { expected: usize, found: usize },
+    /// The string contains a character outside of the encoding's alphabet.
+    InvalidCharacter { character: char, index: usize },
+    /// The encoded number does not fit in the 63 bits of an id.
+    Overflow,
+    /// The check symbol does not match the encoded number, so a symbol was
+    /// mistyped or two symbols were swapped.
+    InvalidCheckSymbol { expected: char, found: char },
+    /// The string does not start with the prefix of the
+    /// [`TypedFastId`](crate::TypedFastId) being parsed.
+    InvalidPrefix { expected: &'static str },
+}
+
+impl fmt::Display for ParseFastIdError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseFastIdError::InvalidLength { expected, found } => {
+                write!(fmt, "expected {} characters but found {}", expected, found)
+            }
+            ParseFastIdError::InvalidCharacter { character, index } => {
+                write!(fmt, "invalid character {:?} at index {}", character, index)
+            }
+            ParseFastIdError::Overflow => write!(fmt, "number too large to be an id"),
+            ParseFastIdError::InvalidCheckSymbol { expected, found } => write!(
+                fmt,
+                "expected check symbol {:?} but found {:?}",
+                expected, found
+            ),
+            ParseFastIdError::InvalidPrefix { expected } => {
+                write!(fmt, "expected prefix {:?}", expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseFastIdError {}
+
+/// The error returned when converting an integer that does not fit in the 63
+/// bits of an id into a [`FastId`](crate::FastId).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TryFromIntError(pub(crate) ());
+
+impl fmt::Display for TryFromIntError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "integer out of range for an id")
+    }
+}
+
+impl std::error::Error for TryFromIntError {}
@@ -0,0 +1,586 @@
+//! GUID forms of ids, enabled by the `guid` feature.
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use uuid::Uuid;
+
+use crate::{FastId, FastIdError, IdLayout};
+
+/// The kind of GUID an id is converted to by [`IdLayout::to_guid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GuidFormat {
+    /// The historic FastID GUID: a version 1 layout carrying raw ticks rather
+    /// than 100ns intervals since 1582, so other UUID libraries decode it to
+    /// meaningless dates.
+    #[default]
+    FastId,
+    /// An [RFC 9562](https://www.rfc-editor.org/rfc/rfc9562#name-uuid-version-7)
+    /// version 7 UUID: milliseconds since the Unix epoch, the sequence in
+    /// `rand_a` and the machine id in `rand_b`.
+    ///
+    /// Needs ticks of at least a millisecond and at most 12 sequence bits.
+    V7,
+    /// An [RFC 9562](https://www.rfc-editor.org/rfc/rfc9562#name-uuid-version-1)
+    /// version 1 UUID: 100ns intervals since 1582-10-15, the sequence as the
+    /// clock sequence and the machine id as the node, with the multicast bit
+    /// set to mark it as not being a MAC address.
+    ///
+    /// Needs ticks of at least 100ns and machine ids below 2^40.
+    V1,
+    /// An [RFC 9562](https://www.rfc-editor.org/rfc/rfc9562#name-uuid-version-6)
+    /// version 6 UUID: a [`GuidFormat::V1`] UUID with the timestamp reordered
+    /// so that UUIDs sort by time.
+    ///
+    /// Needs ticks of at least 100ns and machine ids below 2^40.
+    V6,
+}
+
+/// 100ns intervals between 1582-10-15 and the Unix epoch.
+const GREGORIAN_OFFSET: u64 = 0x01B2_1DD2_1381_4000;
+
+/// The multicast bit of a node id, set when the node is not a MAC address.
+const MULTICAST: u64 = 1 << 40;
+
+impl GuidFormat {
+    /// Checks that GUIDs of this format built from ids of `layout` and
+    /// `machine_id` are unique.
+    pub(crate) fn validate(&self, layout: &IdLayout, machine_id: u64) -> Result<(), FastIdError> {
+        let max = match self {
+            GuidFormat::FastId | GuidFormat::V1 | GuidFormat::V6 => 14,
+            GuidFormat::V7 => 12,
+        };
+        if layout.sequence_bits() > max {
+            return Err(FastIdError::SequenceTooWide {
+                bits: layout.sequence_bits(),
+                max,
+            });
+        }
+
+        // ticks sharing a timestamp would reuse the same sequence numbers
+        let min = match self {
+            GuidFormat::FastId => Duration::new(0, 0),
+            GuidFormat::V7 => Duration::from_millis(1),
+            GuidFormat::V1 | GuidFormat::V6 => Duration::from_nanos(100),
+        };
+        if layout.tick().as_duration() < min {
+            return Err(FastIdError::TickTooShort {
+                tick: layout.tick().as_duration(),
+                min,
+            });
+        }
+
+        // the multicast bit would hide the highest bits of the machine id
+        if matches!(self, GuidFormat::V1 | GuidFormat::V6) && machine_id >= MULTICAST {
+            return Err(FastIdError::MachineIdTooLarge {
+                machine_id,
+                max: MULTICAST - 1,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn version(&self) -> usize {
+        match self {
+            GuidFormat::FastId | GuidFormat::V1 => 1,
+            GuidFormat::V6 => 6,
+            GuidFormat::V7 => 7,
+        }
+    }
+}
+
+impl IdLayout {
+    /// Returns the GUID of the given format embedding the timestamp, sequence
+    /// and machine id of an id with this layout.
+    ///
+    /// Fails if the GUID would not be unique, e.g. if the layout has more
+    /// sequence bits than the format can hold.
+    pub fn to_guid(&self, id: &FastId, format: GuidFormat) -> Result<Uuid, FastIdError> {
+        let (ticks, sequence, machine_id) = self.unpack(id.as_u64());
+
+        format.validate(self, machine_id)?;
+
+        Ok(self.guid(format, ticks, sequence, machine_id))
+    }
+
+    /// Returns the id of this layout a GUID of the given format was built
+    /// from, the reverse of [`IdLayout::to_guid`].
+    ///
+    /// Fails if the version or variant bits do not match the format, or if
+    /// the GUID could not have been built from an id of this layout.
+    ///
+    /// ```
+    /// use fastid::{FastIdWorker, GuidFormat};
+    ///
+    /// let worker = FastIdWorker::new(7);
+    /// let id = worker.next_id();
+    /// let guid = worker.to_guid(&id).unwrap();
+    ///
+    /// let layout = worker.layout();
+    /// assert_eq!(layout.from_guid(&guid, GuidFormat::FastId), Ok(id));
+    /// assert_eq!(layout.decode(&id).machine_id, 7);
+    /// ```
+    pub fn from_guid(&self, guid: &Uuid, format: GuidFormat) -> Result<FastId, FastIdError> {
+        if guid.get_version_num() != format.version() {
+            return Err(FastIdError::GuidVersion {
+                expected: format.version(),
+                found: guid.get_version_num(),
+            });
+        }
+        if guid.get_variant() != uuid::Variant::RFC4122 {
+            return Err(FastIdError::GuidVariant);
+        }
+
+        let (ticks, sequence, machine_id) = match format {
+            GuidFormat::FastId => self.fastid_fields(guid),
+            GuidFormat::V7 => self.v7_fields(guid),
+            GuidFormat::V1 | GuidFormat::V6 => self.gregorian_fields(guid, format),
+        }
+        .ok_or(FastIdError::GuidOutOfRange)?;
+
+        if ticks > self.max_ticks()
+            || sequence > self.max_sequence()
+            || machine_id > self.max_machine_id()
+        {
+            return Err(FastIdError::GuidOutOfRange);
+        }
+
+        let id = FastId::from_raw(self.pack(ticks, sequence, machine_id) as i64);
+
+        // catches bits the fields above ignore, like the low tick bits the
+        // FastID format copies into the clock sequence
+        if self.to_guid(&id, format)? != *guid {
+            return Err(FastIdError::GuidOutOfRange);
+        }
+
+        Ok(id)
+    }
+
+    fn guid(&self, format: GuidFormat, ticks: u64, sequence: u64, machine_id: u64) -> Uuid {
+        match format {
+            GuidFormat::FastId => self.fastid_guid(ticks, sequence, machine_id),
+            GuidFormat::V7 => self.v7_guid(ticks, sequence, machine_id),
+            GuidFormat::V1 => self.v1_guid(ticks, sequence, machine_id),
+            GuidFormat::V6 => self.v6_guid(ticks, sequence, machine_id),
+        }
+    }
+
+    fn fastid_guid(&self, ticks: u64, sequence: u64, machine_id: u64) -> Uuid {
+        // codes from https://github.com/uuid-rs/uuid/blob/805f4edd4d356dc05b5be55397f7fb43e47a78eb/src/v1.rs#L195-L216
+
+        let time_low = (ticks & 0xFFFF_FFFF) as u32;
+        let time_mid = ((ticks >> 32) & 0xFFFF) as u16;
+        let time_high_and_version = (((ticks >> 48) & 0x0FFF) as u16) | (1 << 12);
+
+        let mut d4 = [0; 8];
+
+        let placeholder_bits = 14 - self.sequence_bits();
+        let placeholder_mask = !(u64::MAX << placeholder_bits);
+
+        let sequence = (sequence << placeholder_bits) | (ticks & placeholder_mask);
+
+        d4[0] = (((sequence & 0x3F00) >> 8) as u8) | 0x80;
+        d4[1] = (sequence & 0xFF) as u8;
+
+        let node_id = u64::to_be_bytes(machine_id & 0xFFFF_FFFF_FFFF);
+        d4[2..].copy_from_slice(&node_id[2..]);
+
+        Uuid::from_fields(time_low, time_mid, time_high_and_version, &d4)
+    }
+
+    fn fastid_fields(&self, guid: &Uuid) -> Option<(u64, u64, u64)> {
+        let (time_low, time_mid, time_high_and_version, d4) = guid.as_fields();
+
+        let ticks = (time_low as u64)
+            | ((time_mid as u64) << 32)
+            | (((time_high_and_version & 0x0FFF) as u64) << 48);
+
+        let placeholder_bits = 14usize.checked_sub(self.sequence_bits())?;
+        let sequence = ((((d4[0] & 0x3F) as u64) << 8) | d4[1] as u64) >> placeholder_bits;
+
+        let mut node_id = [0; 8];
+        node_id[2..].copy_from_slice(&d4[2..]);
+
+        Some((ticks, sequence, u64::from_be_bytes(node_id)))
+    }
+
+    fn v7_guid(&self, ticks: u64, sequence: u64, machine_id: u64) -> Uuid {
+        let millis = self
+            .timestamp_at(ticks)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::new(0, 0))
+            .as_millis() as u64;
+
+        let bits = ((millis as u128 & 0xFFFF_FFFF_FFFF) << 80)
+            | (0x7 << 76)
+            | ((sequence as u128 & 0xFFF) << 64)
+            | (0b10 << 62)
+            | (machine_id as u128 & 0x3FFF_FFFF_FFFF_FFFF);
+
+        Uuid::from_u128(bits)
+    }
+
+    fn v7_fields(&self, guid: &Uuid) -> Option<(u64, u64, u64)> {
+        let bits = guid.as_u128();
+
+        let millis = (bits >> 80) as u64;
+        let ticks = self.ticks_since_unix_epoch(millis as u128 * 1_000_000)?;
+
+        Some((
+            ticks,
+            (bits >> 64) as u64 & 0xFFF,
+            bits as u64 & 0x3FFF_FFFF_FFFF_FFFF,
+        ))
+    }
+
+    /// Returns the tick starting at most one tick after `nanos`, which is
+    /// the tick a timestamp truncated to a coarser precision than the tick
+    /// was taken from.
+    fn ticks_since_unix_epoch(&self, nanos: u128) -> Option<u64> {
+        let tick = self.tick().as_nanos() as i128;
+        let epoch = self.epoch().duration_since(UNIX_EPOCH).ok()?.as_nanos();
+        let since_epoch = nanos as i128 - epoch as i128;
+
+        if since_epoch <= -tick {
+            return None;
+        }
+
+        u64::try_from((since_epoch.max(0) + tick - 1) / tick).ok()
+    }
+
+    /// Returns the 100ns intervals since 1582-10-15 at which the tick starts.
+    fn gregorian_timestamp(&self, ticks: u64) -> u64 {
+        let intervals = self
+            .timestamp_at(ticks)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::new(0, 0))
+            .as_nanos()
+            / 100;
+
+        (intervals as u64 + GREGORIAN_OFFSET) & 0x0FFF_FFFF_FFFF_FFFF
+    }
+
+    /// Returns the clock sequence and node shared by version 1 and 6 UUIDs.
+    fn clock_sequence_and_node(sequence: u64, machine_id: u64) -> u128 {
+        (0b10 << 62)
+            | ((sequence as u128 & 0x3FFF) << 48)
+            | ((machine_id | MULTICAST) as u128 & 0xFFFF_FFFF_FFFF)
+    }
+
+    fn gregorian_fields(&self, guid: &Uuid, format: GuidFormat) -> Option<(u64, u64, u64)> {
+        let bits = guid.as_u128();
+
+        let timestamp = if format == GuidFormat::V6 {
+            (bits >> 80 << 12) as u64 | ((bits >> 64) as u64 & 0x0FFF)
+        } else {
+            ((bits >> 96) as u64)
+                | (((bits >> 80) as u64 & 0xFFFF) << 32)
+                | (((bits >> 64) as u64 & 0x0FFF) << 48)
+        };
+        let intervals = timestamp.checked_sub(GREGORIAN_OFFSET)?;
+        let ticks = self.ticks_since_unix_epoch(intervals as u128 * 100)?;
+
+        Some((
+            ticks,
+            (bits >> 48) as u64 & 0x3FFF,
+            bits as u64 & 0xFFFF_FFFF_FFFF & !MULTICAST,
+        ))
+    }
+
+    fn v1_guid(&self, ticks: u64, sequence: u64, machine_id: u64) -> Uuid {
+        let timestamp = self.gregorian_timestamp(ticks) as u128;
+
+        let bits = ((timestamp & 0xFFFF_FFFF) << 96)
+            | ((timestamp >> 32 & 0xFFFF) << 80)
+            | (0x1 << 76)
+            | ((timestamp >> 48 & 0x0FFF) << 64)
+            | IdLayout::clock_sequence_and_node(sequence, machine_id);
+
+        Uuid::from_u128(bits)
+    }
+
+    fn v6_guid(&self, ticks: u64, sequence: u64, machine_id: u64) -> Uuid {
+        let timestamp = self.gregorian_timestamp(ticks) as u128;
+
+        let bits = ((timestamp >> 12) << 80)
+            | (0x6 << 76)
+            | ((timestamp & 0x0FFF) << 64)
+            | IdLayout::clock_sequence_and_node(sequence, machine_id);
+
+        Uuid::from_u128(bits)
+    }
+}
+
+/// Recovers an id from a GUID built with [`IdLayout::default`] and
+/// [`GuidFormat::FastId`], see [`IdLayout::from_guid`] for other layouts and
+/// formats.
+impl TryFrom<Uuid> for FastId {
+    type Error = FastIdError;
+
+    fn try_from(guid: Uuid) -> Result<Self, Self::Error> {
+        IdLayout::default().from_guid(&guid, GuidFormat::FastId)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FastIdWorker, MockClock, TickResolution};
+
+    #[test]
+    fn can_build_v7_uuids() {
+        let now = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let layout = IdLayout::new(41, 10, 12)
+            .unwrap()
+            .with_tick(TickResolution::MILLISECOND);
+        let worker = FastIdWorker::builder()
+            .clock(MockClock::new(now))
+            .layout(layout)
+            .machine_id(0x2AB)
+            .guid_format(GuidFormat::V7)
+            .build()
+            .unwrap();
+
+        let first = worker.to_guid(&worker.next_id()).unwrap();
+        let second = worker.to_guid(&worker.next_id()).unwrap();
+
+        assert_eq!(first.get_version_num(), 7);
+        assert_eq!(first.get_variant(), uuid::Variant::RFC4122);
+        assert_eq!(
+            first.get_timestamp().map(|ts| ts.to_unix()),
+            Some((1_700_000_000, 123_000_000))
+        );
+        assert_eq!(first.as_u128() >> 64 & 0xFFF, 0);
+        assert_eq!(second.as_u128() >> 64 & 0xFFF, 1);
+        assert_eq!(first.as_u128() & 0x3FFF_FFFF_FFFF_FFFF, 0x2AB);
+        assert!(first < second);
+    }
+
+    #[test]
+    fn can_build_v1_and_v6_uuids() {
+        let now = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_700);
+        // 2^40 ticks of 100ns only last about 30 hours
+        let layout = IdLayout::default()
+            .with_tick(TickResolution::from_nanos(100))
+            .with_epoch(1_699_999_000_000_000_000);
+
+        for (format, version) in [(GuidFormat::V1, 1), (GuidFormat::V6, 6)] {
+            let worker = FastIdWorker::builder()
+                .clock(MockClock::new(now))
+                .layout(layout)
+                .machine_id(0xBEEF)
+                .guid_format(format)
+                .build()
+                .unwrap();
+
+            let first = worker.to_guid(&worker.next_id()).unwrap();
+            let second = worker.to_guid(&worker.next_id()).unwrap();
+
+            assert_eq!(first.get_version_num(), version);
+            assert_eq!(first.get_variant(), uuid::Variant::RFC4122);
+            assert_eq!(
+                first.get_timestamp().map(|ts| ts.to_unix()),
+                Some((1_700_000_000, 123_456_700))
+            );
+
+            let (_, clock_sequence) = first.get_timestamp().unwrap().to_gregorian();
+            assert_eq!(clock_sequence, 0);
+            let (_, clock_sequence) = second.get_timestamp().unwrap().to_gregorian();
+            assert_eq!(clock_sequence, 1);
+
+            assert_eq!(
+                first.as_fields().3[2..],
+                [0x01, 0x00, 0x00, 0x00, 0xBE, 0xEF]
+            );
+        }
+    }
+
+    #[test]
+    fn v6_uuids_sort_by_time() {
+        let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let layout = IdLayout::default()
+            .with_tick(TickResolution::from_nanos(100))
+            .with_epoch(1_699_999_000_000_000_000);
+
+        let guids = |format| {
+            let worker = FastIdWorker::builder()
+                .clock(clock.clone())
+                .layout(layout)
+                .guid_format(format)
+                .build()
+                .unwrap();
+
+            let first = worker.to_guid(&worker.next_id()).unwrap();
+            // wraps time_low, the most significant field of a v1 UUID
+            clock.advance(Duration::from_nanos(((1 << 32) - 1) * 100));
+            let second = worker.to_guid(&worker.next_id()).unwrap();
+            clock.set(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+
+            (first, second)
+        };
+
+        let (first, second) = guids(GuidFormat::V1);
+        assert!(first > second);
+
+        let (first, second) = guids(GuidFormat::V6);
+        assert!(first < second);
+    }
+
+    #[test]
+    fn rejects_machine_ids_hidden_by_the_multicast_bit() {
+        let result = FastIdWorker::builder()
+            .layout(IdLayout::new(40, 16, 7).unwrap())
+            .machine_id(0xFFFF)
+            .guid_format(GuidFormat::V1)
+            .build();
+        assert!(result.is_ok());
+
+        let result = FastIdWorker::builder()
+            .layout(IdLayout::new(15, 41, 7).unwrap())
+            .machine_id(1 << 40)
+            .guid_format(GuidFormat::V6)
+            .build();
+        assert_eq!(
+            result.err(),
+            Some(FastIdError::MachineIdTooLarge {
+                machine_id: 1 << 40,
+                max: (1 << 40) - 1,
+            })
+        );
+    }
+
+    #[test]
+    fn can_build_guids_without_a_worker() {
+        let layout = IdLayout::default();
+        let id = FastId::try_from(layout.pack(0x12_3456_789A, 0x5A, 0xBEEF) as i64).unwrap();
+
+        let guid = layout.to_guid(&id, GuidFormat::FastId).unwrap();
+        assert_eq!(guid.to_string(), "3456789a-0012-1000-ad1a-00000000beef");
+        assert_eq!(id.to_guid(&layout, GuidFormat::FastId), Ok(guid));
+
+        let layout = IdLayout::new(40, 8, 15).unwrap();
+        assert_eq!(
+            layout.to_guid(&id, GuidFormat::FastId),
+            Err(FastIdError::SequenceTooWide { bits: 15, max: 14 })
+        );
+    }
+
+    #[test]
+    fn rejects_layouts_that_would_collide_as_v7() {
+        let result = FastIdWorker::builder()
+            .layout(IdLayout::new(41, 9, 13).unwrap())
+            .guid_format(GuidFormat::V7)
+            .build();
+        assert_eq!(
+            result.err(),
+            Some(FastIdError::SequenceTooWide { bits: 13, max: 12 })
+        );
+
+        let result = FastIdWorker::builder()
+            .layout(IdLayout::default().with_tick(TickResolution::from_nanos(500_000)))
+            .guid_format(GuidFormat::V7)
+            .build();
+        assert_eq!(
+            result.err(),
+            Some(FastIdError::TickTooShort {
+                tick: Duration::from_micros(500),
+                min: Duration::from_millis(1),
+            })
+        );
+    }
+
+    #[test]
+    fn can_recover_ids_from_guids() {
+        let now = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+        let layouts = [
+            (GuidFormat::FastId, IdLayout::default()),
+            (GuidFormat::FastId, IdLayout::new(41, 10, 12).unwrap()),
+            (
+                GuidFormat::V7,
+                IdLayout::new(41, 10, 12)
+                    .unwrap()
+                    .with_tick(TickResolution::MILLISECOND),
+            ),
+            (
+                GuidFormat::V7,
+                IdLayout::new(32, 16, 12)
+                    .unwrap()
+                    .with_tick(TickResolution::SECOND)
+                    .with_epoch(1_600_000_000_123_456_789),
+            ),
+            (
+                GuidFormat::V1,
+                IdLayout::default()
+                    .with_tick(TickResolution::from_nanos(100))
+                    .with_epoch(1_699_999_000_000_000_050),
+            ),
+            (
+                GuidFormat::V6,
+                IdLayout::default()
+                    .with_tick(TickResolution::from_nanos(1_000))
+                    .with_epoch(1_699_000_000_000_000_000),
+            ),
+        ];
+
+        for (format, layout) in layouts {
+            let worker = FastIdWorker::builder()
+                .clock(MockClock::new(now))
+                .layout(layout)
+                .machine_id(0x2A)
+                .guid_format(format)
+                .build()
+                .unwrap();
+
+            for _ in 0..3 {
+                let id = worker.next_id();
+                let guid = worker.to_guid(&id).unwrap();
+
+                assert_eq!(worker.from_guid(&guid), Ok(id), "{:?}", format);
+                assert_eq!(layout.decode(&id).machine_id, 0x2A);
+            }
+        }
+    }
+
+    #[test]
+    fn can_convert_default_guids_into_ids() {
+        let layout = IdLayout::default();
+        let id = FastId::try_from(layout.pack(0x12_3456_789A, 0x5A, 0xBEEF) as i64).unwrap();
+
+        let guid = Uuid::parse_str("3456789a-0012-1000-ad1a-00000000beef").unwrap();
+        assert_eq!(FastId::try_from(guid), Ok(id));
+    }
+
+    #[test]
+    fn rejects_guids_not_built_from_the_layout() {
+        let v4 = Uuid::parse_str("3456789a-0012-4000-ad1a-00000000beef").unwrap();
+        assert_eq!(
+            FastId::try_from(v4),
+            Err(FastIdError::GuidVersion {
+                expected: 1,
+                found: 4
+            })
+        );
+
+        let microsoft = Uuid::parse_str("3456789a-0012-1000-cd1a-00000000beef").unwrap();
+        assert_eq!(FastId::try_from(microsoft), Err(FastIdError::GuidVariant));
+
+        // the low tick bits copied into the clock sequence disagree
+        let tampered = Uuid::parse_str("3456789a-0012-1000-ad1b-00000000beef").unwrap();
+        assert_eq!(FastId::try_from(tampered), Err(FastIdError::GuidOutOfRange));
+
+        // the machine id does not fit in 16 bits
+        let foreign = Uuid::parse_str("3456789a-0012-1000-ad1a-00000001beef").unwrap();
+        assert_eq!(FastId::try_from(foreign), Err(FastIdError::GuidOutOfRange));
+
+        // a v1 UUID from 2022, before the epoch of the layout
+        let v1 = Uuid::parse_str("c232ab00-9414-11ec-b3c8-9f6bdeced846").unwrap();
+        let layout = IdLayout::new(15, 41, 7)
+            .unwrap()
+            .with_tick(TickResolution::from_nanos(100))
+            .with_epoch(1_700_000_000_000_000_000);
+        assert_eq!(
+            layout.from_guid(&v1, GuidFormat::V1),
+            Err(FastIdError::GuidOutOfRange)
+        );
+    }
+}
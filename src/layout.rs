@@ -0,0 +1,681 @@
+use std::ops::Add;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{FastId, FastIdError, FastIdParts, DEFAULT_EPOCH};
+
+/// The order in which the fields are packed into an id, from the most to the
+/// least significant bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FieldOrder {
+    /// `time | sequence | machine`, the order used by FastID.
+    #[default]
+    TimeSequenceMachine,
+    /// `time | machine | sequence`, the order used by Twitter Snowflake, which
+    /// keeps the ids of one machine contiguous.
+    TimeMachineSequence,
+}
+
+/// The length of one tick of the time field.
+///
+/// Longer ticks make the time field last longer but leave fewer ids per unit
+/// of time, as every tick has the same number of sequence numbers. With 40
+/// time bits, 1ms ticks last about 34 years and 10ms ticks about 348 years.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TickResolution {
+    nanos: u64,
+}
+
+impl TickResolution {
+    /// 2^20 nanoseconds (~1.049ms), the resolution used by FastID.
+    pub const DEFAULT: TickResolution = TickResolution::from_pow2_nanos(20);
+
+    pub const MILLISECOND: TickResolution = TickResolution::from_millis(1);
+
+    pub const SECOND: TickResolution = TickResolution::from_secs(1);
+
+    /// Ticks of `nanos` nanoseconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nanos` is 0.
+    pub const fn from_nanos(nanos: u64) -> Self {
+        assert!(nanos > 0, "tick resolution must not be zero");
+
+        TickResolution { nanos }
+    }
+
+    /// Ticks of 2^`shift` nanoseconds, which are converted from nanoseconds
+    /// with a shift rather than a division.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shift` is 64 or more.
+    pub const fn from_pow2_nanos(shift: u32) -> Self {
+        assert!(shift < 64, "tick resolution must fit in 64 bits");
+
+        TickResolution::from_nanos(1 << shift)
+    }
+
+    /// Ticks of `millis` milliseconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `millis` is 0 or the resolution does not fit in 64 bits of
+    /// nanoseconds.
+    pub const fn from_millis(millis: u64) -> Self {
+        assert!(
+            millis <= u64::MAX / 1_000_000,
+            "tick resolution must fit in 64 bits"
+        );
+
+        TickResolution::from_nanos(millis * 1_000_000)
+    }
+
+    /// Ticks of `secs` seconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `secs` is 0 or the resolution does not fit in 64 bits of
+    /// nanoseconds.
+    pub const fn from_secs(secs: u64) -> Self {
+        assert!(
+            secs <= u64::MAX / 1_000_000_000,
+            "tick resolution must fit in 64 bits"
+        );
+
+        TickResolution::from_nanos(secs * 1_000_000_000)
+    }
+
+    pub const fn as_nanos(&self) -> u64 {
+        self.nanos
+    }
+
+    pub const fn as_duration(&self) -> Duration {
+        Duration::from_nanos(self.nanos)
+    }
+
+    /// Returns the number of whole ticks in `duration`.
+    fn ticks_in(&self, duration: Duration) -> u64 {
+        let nanos = duration.as_nanos();
+
+        if self.nanos.is_power_of_two() {
+            (nanos >> self.nanos.trailing_zeros()) as u64
+        } else {
+            (nanos / self.nanos as u128) as u64
+        }
+    }
+}
+
+impl Default for TickResolution {
+    fn default() -> Self {
+        TickResolution::DEFAULT
+    }
+}
+
+/// Describes how an id is laid out: the width and order of its fields, the
+/// length of a tick and the epoch ticks are counted from.
+///
+/// A layout is all that is needed to decode ids, so nodes that only read ids
+/// can share it with the workers that generate them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IdLayout {
+    time_bits: usize,
+    machine_bits: usize,
+    sequence_bits: usize,
+
+    order: FieldOrder,
+    tick: TickResolution,
+
+    epoch: u64,
+}
+
+impl IdLayout {
+    /// [Twitter Snowflake](https://github.com/twitter-archive/snowflake): 41
+    /// bits of milliseconds since 2010-11-04, a 10-bit machine id (datacenter
+    /// and worker) and a 12-bit sequence, in that order.
+    pub const TWITTER: IdLayout = IdLayout::preset(
+        41,
+        10,
+        12,
+        FieldOrder::TimeMachineSequence,
+        TickResolution::from_millis(1),
+        1_288_834_974_657,
+    );
+
+    /// [Sonyflake](https://github.com/sony/sonyflake): 39 bits of 10ms ticks
+    /// since 2014-09-01, an 8-bit sequence and a 16-bit machine id, in that
+    /// order.
+    pub const SONYFLAKE: IdLayout = IdLayout::preset(
+        39,
+        16,
+        8,
+        FieldOrder::TimeSequenceMachine,
+        TickResolution::from_millis(10),
+        1_409_529_600_000,
+    );
+
+    /// [Discord](https://discord.com/developers/docs/reference#snowflakes):
+    /// milliseconds since 2015-01-01, a 10-bit machine id (internal worker and
+    /// process ids) and a 12-bit increment, in that order.
+    ///
+    /// Discord reserves 42 bits for the timestamp, of which only 41 are used
+    /// before 2084, so the sign bit is left out here.
+    pub const DISCORD: IdLayout = IdLayout::preset(
+        41,
+        10,
+        12,
+        FieldOrder::TimeMachineSequence,
+        TickResolution::from_millis(1),
+        1_420_070_400_000,
+    );
+
+    /// [Instagram](https://instagram-engineering.com/sharding-ids-at-instagram-1cf5a71e5a5c):
+    /// milliseconds since 2011-08-24, a 13-bit logical shard id as the machine
+    /// id and a 10-bit sequence, in that order.
+    ///
+    /// Instagram reserves 41 bits for the timestamp, of which only 40 are used
+    /// before 2046, when its ids turn negative, so the sign bit is left out
+    /// here.
+    pub const INSTAGRAM: IdLayout = IdLayout::preset(
+        40,
+        13,
+        10,
+        FieldOrder::TimeMachineSequence,
+        TickResolution::from_millis(1),
+        1_314_220_021_721,
+    );
+
+    /// [Mastodon](https://github.com/mastodon/mastodon/blob/main/lib/mastodon/snowflake.rb):
+    /// milliseconds since the Unix epoch above 16 bits of sequence data and no
+    /// machine id.
+    ///
+    /// Mastodon reserves 48 bits for the timestamp, of which only 47 are used
+    /// before the year 6429, so the sign bit is left out here.
+    pub const MASTODON: IdLayout = IdLayout::preset(
+        47,
+        0,
+        16,
+        FieldOrder::TimeSequenceMachine,
+        TickResolution::from_millis(1),
+        0,
+    );
+
+    const fn preset(
+        time_bits: usize,
+        machine_bits: usize,
+        sequence_bits: usize,
+        order: FieldOrder,
+        tick: TickResolution,
+        epoch_millis: u64,
+    ) -> Self {
+        match IdLayout::new(time_bits, machine_bits, sequence_bits) {
+            Ok(layout) => layout
+                .with_order(order)
+                .with_tick(tick)
+                .with_epoch(epoch_millis * 1_000_000),
+            Err(_) => panic!("invalid preset layout"),
+        }
+    }
+
+    /// Creates a layout with the default order, tick resolution and epoch.
+    ///
+    /// Fails if the fields do not fit in the 63 bits below the sign bit.
+    pub const fn new(
+        time_bits: usize,
+        machine_bits: usize,
+        sequence_bits: usize,
+    ) -> Result<Self, FastIdError> {
+        let total = time_bits
+            .saturating_add(machine_bits)
+            .saturating_add(sequence_bits);
+        if total > 63 {
+            return Err(FastIdError::TooManyBits { total });
+        }
+
+        Ok(IdLayout {
+            time_bits,
+            machine_bits,
+            sequence_bits,
+
+            order: FieldOrder::TimeSequenceMachine,
+            tick: TickResolution::DEFAULT,
+
+            epoch: DEFAULT_EPOCH,
+        })
+    }
+
+    /// Counts ticks from `epoch`, given in nanoseconds since the Unix epoch.
+    pub const fn with_epoch(mut self, epoch: u64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Counts time in ticks of the given resolution.
+    pub const fn with_tick(mut self, tick: TickResolution) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Packs the fields in the given order.
+    pub const fn with_order(mut self, order: FieldOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Takes `bits` from the top of the sequence field, which the shards of a
+    /// [`ShardedFastIdWorker`](crate::ShardedFastIdWorker) fill in.
+    pub(crate) fn without_sequence_bits(mut self, bits: usize) -> Self {
+        self.sequence_bits -= bits;
+        self
+    }
+
+    pub const fn time_bits(&self) -> usize {
+        self.time_bits
+    }
+
+    pub const fn machine_bits(&self) -> usize {
+        self.machine_bits
+    }
+
+    pub const fn sequence_bits(&self) -> usize {
+        self.sequence_bits
+    }
+
+    pub const fn order(&self) -> FieldOrder {
+        self.order
+    }
+
+    pub const fn tick(&self) -> TickResolution {
+        self.tick
+    }
+
+    pub fn epoch(&self) -> SystemTime {
+        UNIX_EPOCH.add(Duration::from_nanos(self.epoch))
+    }
+
+    pub const fn max_machine_id(&self) -> u64 {
+        mask(self.machine_bits)
+    }
+
+    pub const fn max_sequence(&self) -> u64 {
+        mask(self.sequence_bits)
+    }
+
+    pub const fn max_ticks(&self) -> u64 {
+        mask(self.time_bits)
+    }
+
+    /// Returns the number of whole ticks between the epoch and `now`, or 0 if
+    /// `now` is before the epoch.
+    pub fn ticks_at(&self, now: SystemTime) -> u64 {
+        let duration = now
+            .duration_since(self.epoch())
+            .unwrap_or(Duration::new(0, 0));
+
+        self.tick.ticks_in(duration)
+    }
+
+    /// Returns the instant at which the given tick starts.
+    ///
+    /// Where [`SystemTime`] is coarser than a nanosecond, as on Windows where
+    /// it counts 100ns intervals, this is the first instant it can represent
+    /// in the tick, so that [`IdLayout::ticks_at`] maps it back to the tick.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instant cannot be represented by [`SystemTime`].
+    pub fn timestamp_at(&self, ticks: u64) -> SystemTime {
+        self.checked_timestamp_at(ticks as u128)
+            .expect("timestamp out of range")
+    }
+
+    fn checked_timestamp_at(&self, ticks: u128) -> Option<SystemTime> {
+        let nanos = ticks * self.tick.nanos as u128;
+        let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+        let duration = Duration::new(secs, (nanos % 1_000_000_000) as u32);
+
+        let start = self.epoch().checked_add(duration)?;
+
+        // adding a duration truncates it to the resolution of the clock, so
+        // round up by growing steps until the instant is no longer early
+        let mut instant = start;
+        let mut step = 1;
+        while instant.duration_since(self.epoch()).ok()? < duration && step <= 1_000_000_000 {
+            instant = start.checked_add(Duration::from_nanos(step))?;
+            step *= 10;
+        }
+
+        Some(instant)
+    }
+
+    /// Returns the instant at which the time field runs out, after which no
+    /// more ids can be generated with this layout, or `None` if it is too far
+    /// in the future to be represented by [`SystemTime`].
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.checked_timestamp_at(self.max_ticks() as u128 + 1)
+    }
+
+    /// Returns how far the sequence and machine fields are shifted.
+    const fn shifts(&self) -> (usize, usize) {
+        match self.order {
+            FieldOrder::TimeSequenceMachine => (self.machine_bits, 0),
+            FieldOrder::TimeMachineSequence => (0, self.sequence_bits),
+        }
+    }
+
+    pub(crate) fn pack(&self, ticks: u64, sequence: u64, machine_id: u64) -> u64 {
+        let (sequence_shift, machine_shift) = self.shifts();
+
+        ((ticks & self.max_ticks()) << (self.machine_bits + self.sequence_bits))
+            | ((sequence & self.max_sequence()) << sequence_shift)
+            | ((machine_id & self.max_machine_id()) << machine_shift)
+    }
+
+    /// Splits an id back into its ticks, sequence and machine id.
+    pub(crate) fn unpack(&self, id: u64) -> (u64, u64, u64) {
+        let (sequence_shift, machine_shift) = self.shifts();
+
+        let ticks = (id >> (self.machine_bits + self.sequence_bits)) & self.max_ticks();
+        let sequence = (id >> sequence_shift) & self.max_sequence();
+        let machine_id = (id >> machine_shift) & self.max_machine_id();
+
+        (ticks, sequence, machine_id)
+    }
+
+    /// Decodes an id generated with this layout back into the instant it was
+    /// generated at, its sequence and its machine id.
+    ///
+    /// The timestamp is truncated to the tick the id was generated in, and
+    /// saturates at the latest instant [`SystemTime`] can represent for ticks
+    /// that start after it.
+    pub fn decode(&self, id: &FastId) -> FastIdParts {
+        let (ticks, sequence, machine_id) = self.unpack(id.as_u64());
+
+        FastIdParts {
+            timestamp: self
+                .checked_timestamp_at(ticks as u128)
+                .unwrap_or_else(latest_system_time),
+            sequence,
+            machine_id,
+        }
+    }
+}
+
+impl Default for IdLayout {
+    /// 40 time bits, 16 machine bits and 7 sequence bits counted in
+    /// [`TickResolution::DEFAULT`] ticks from [`DEFAULT_EPOCH`].
+    fn default() -> Self {
+        match IdLayout::new(40, 16, 7) {
+            Ok(layout) => layout,
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+/// Returns the latest whole second [`SystemTime`] can represent, which
+/// depends on the platform.
+fn latest_system_time() -> SystemTime {
+    let (mut low, mut high) = (0, u64::MAX);
+    while low < high {
+        let mid = high - (high - low) / 2;
+
+        if UNIX_EPOCH.checked_add(Duration::from_secs(mid)).is_some() {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    UNIX_EPOCH + Duration::from_secs(low)
+}
+
+const fn mask(bits: usize) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        u64::MAX >> (64 - bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the first instant [`SystemTime`] can represent `nanos` after
+    /// the Unix epoch or later, which is coarser than a nanosecond on Windows.
+    fn first_instant_at(nanos: u128) -> SystemTime {
+        let resolution = [1, 10, 100]
+            .into_iter()
+            .find(|&n| UNIX_EPOCH + Duration::from_nanos(n) > UNIX_EPOCH)
+            .expect("resolution of SystemTime is at most 100ns") as u128;
+        let nanos = nanos.div_ceil(resolution) * resolution;
+
+        UNIX_EPOCH
+            + Duration::new(
+                (nanos / 1_000_000_000) as u64,
+                (nanos % 1_000_000_000) as u32,
+            )
+    }
+
+    #[test]
+    fn rejects_too_many_bits() {
+        assert_eq!(
+            IdLayout::new(41, 16, 7),
+            Err(FastIdError::TooManyBits { total: 64 })
+        );
+        assert_eq!(
+            IdLayout::new(64, 0, 0),
+            Err(FastIdError::TooManyBits { total: 64 })
+        );
+        assert!(IdLayout::new(63, 0, 0).is_ok());
+    }
+
+    #[test]
+    fn can_pack_and_unpack() {
+        let layout = IdLayout::new(40, 16, 7).unwrap();
+
+        let id = layout.pack(0x12_3456_789A, 0x5A, 0xBEEF);
+        assert_eq!(id, (0x12_3456_789A << 23) | (0x5A << 16) | 0xBEEF);
+        assert_eq!(layout.unpack(id), (0x12_3456_789A, 0x5A, 0xBEEF));
+    }
+
+    #[test]
+    fn can_pack_time_machine_sequence() {
+        let layout = IdLayout::new(41, 10, 12)
+            .unwrap()
+            .with_order(FieldOrder::TimeMachineSequence);
+
+        let id = layout.pack(0x123_4567_89AB, 0xABC, 0x2F5);
+        assert_eq!(id, (0x123_4567_89AB << 22) | (0x2F5 << 12) | 0xABC);
+        assert_eq!(layout.unpack(id), (0x123_4567_89AB, 0xABC, 0x2F5));
+    }
+
+    #[test]
+    fn ticks_round_trip_through_timestamps() {
+        let layout = IdLayout::default();
+
+        assert_eq!(layout.ticks_at(layout.epoch()), 0);
+        assert_eq!(layout.ticks_at(UNIX_EPOCH), 0);
+        assert_eq!(layout.ticks_at(layout.timestamp_at(12345)), 12345);
+        assert_eq!(
+            layout.timestamp_at(1),
+            first_instant_at(DEFAULT_EPOCH as u128 + TickResolution::DEFAULT.as_nanos() as u128)
+        );
+        assert_eq!(layout.ticks_at(layout.timestamp_at(1)), 1);
+    }
+
+    #[test]
+    fn ticks_map_to_exact_wall_clock_units() {
+        // representable in the 100ns steps of SystemTime on Windows
+        let now = UNIX_EPOCH + Duration::new(1_700_000_000, 987_654_300);
+
+        for (tick, expected) in [
+            (
+                TickResolution::MILLISECOND,
+                Duration::new(1_700_000_000, 987_000_000),
+            ),
+            (
+                TickResolution::from_millis(10),
+                Duration::new(1_700_000_000, 980_000_000),
+            ),
+            (TickResolution::SECOND, Duration::new(1_700_000_000, 0)),
+            (
+                TickResolution::from_nanos(3),
+                Duration::new(1_700_000_000, 987_654_298),
+            ),
+            (
+                TickResolution::from_pow2_nanos(30),
+                Duration::from_nanos(1_583_248_377 << 30),
+            ),
+        ] {
+            let layout = IdLayout::new(40, 16, 7)
+                .unwrap()
+                .with_tick(tick)
+                .with_epoch(0);
+
+            let ticks = layout.ticks_at(now);
+            assert_eq!(
+                layout.timestamp_at(ticks),
+                first_instant_at(expected.as_nanos())
+            );
+            assert_eq!(layout.ticks_at(layout.timestamp_at(ticks)), ticks);
+        }
+    }
+
+    #[test]
+    fn expires_when_time_field_runs_out() {
+        let layout = IdLayout::new(40, 16, 7).unwrap().with_epoch(0);
+
+        // 2^40 ticks of 2^20ns
+        assert_eq!(layout.expires_at(), Some(first_instant_at(1 << 60)));
+
+        let layout = layout.with_tick(TickResolution::from_pow2_nanos(63));
+        assert_eq!(layout.expires_at(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "tick resolution must not be zero")]
+    fn rejects_zero_tick_resolution() {
+        TickResolution::from_millis(0);
+    }
+
+    fn decode(layout: IdLayout, id: i64) -> (u128, u64, u64) {
+        let parts = layout.decode(&FastId::try_from(id).unwrap());
+        let millis = parts
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        (millis, parts.sequence, parts.machine_id)
+    }
+
+    #[test]
+    fn can_decode_twitter_ids() {
+        // https://twitter.com/elonmusk/status/1585841080431321088, posted at
+        // 2022-10-28T03:49:11Z
+        let (millis, sequence, machine_id) = decode(IdLayout::TWITTER, 1585841080431321088);
+
+        assert_eq!(millis / 1000, 1_666_928_951);
+        assert_eq!(machine_id, 330);
+        assert_eq!(sequence, 0);
+    }
+
+    #[test]
+    fn can_decode_discord_ids() {
+        // the example from https://discord.com/developers/docs/reference#snowflakes
+        let (millis, sequence, machine_id) = decode(IdLayout::DISCORD, 175928847299117063);
+
+        // 2016-04-30 11:18:25.796 UTC
+        assert_eq!(millis, 1_462_015_105_796);
+        // internal worker 1, internal process 0
+        assert_eq!(machine_id, 1 << 5);
+        assert_eq!(sequence, 7);
+    }
+
+    #[test]
+    fn can_decode_instagram_ids() {
+        // the example from the Instagram engineering blog, which counts from
+        // 2011-01-01 rather than the epoch used in production
+        let layout = IdLayout::INSTAGRAM.with_epoch(1_293_840_000_000_000_000);
+        let id = (1387263000 << 23) | (1341 << 10) | (5001 % 1024);
+
+        let (millis, sequence, machine_id) = decode(layout, id);
+
+        assert_eq!(millis, 1_293_840_000_000 + 1_387_263_000);
+        assert_eq!(machine_id, 1341);
+        assert_eq!(sequence, 5001 % 1024);
+    }
+
+    #[test]
+    fn saturates_timestamps_beyond_the_system_clock() {
+        let layout = IdLayout::new(63, 0, 0)
+            .unwrap()
+            .with_tick(TickResolution::SECOND);
+
+        let last = layout.decode(&FastId::try_from(i64::MAX).unwrap());
+        assert_eq!(last.timestamp, latest_system_time());
+        assert!(latest_system_time()
+            .checked_add(Duration::from_secs(1))
+            .is_none());
+
+        let first = layout.decode(&FastId::try_from(1i64).unwrap());
+        assert_eq!(first.timestamp, layout.timestamp_at(1));
+    }
+
+    #[test]
+    fn can_decode_sonyflake_ids() {
+        // the fourth id generated at 2023-11-14T22:13:20.12Z by a worker on
+        // 192.168.1.42, whose default machine id is the lower 16 bits of its
+        // private IP address
+        let (millis, sequence, machine_id) = decode(IdLayout::SONYFLAKE, 487_328_464_442_163_498);
+
+        assert_eq!(millis, 1_700_000_000_120);
+        assert_eq!(sequence, 3);
+        assert_eq!(machine_id, (1 << 8) | 42);
+    }
+
+    #[test]
+    fn can_decode_mastodon_ids() {
+        // a status from the examples of https://docs.joinmastodon.org, created
+        // at 2019-12-08T03:48:33Z
+        let (millis, sequence, machine_id) = decode(IdLayout::MASTODON, 103270115826048975);
+
+        assert_eq!(millis / 1000, 1_575_776_913);
+        assert_eq!(sequence, 103270115826048975 & 0xFFFF);
+        assert_eq!(machine_id, 0);
+    }
+
+    #[test]
+    fn presets_generate_ids_they_decode() {
+        use crate::{FastIdWorker, MockClock};
+
+        let now = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+
+        for layout in [
+            IdLayout::TWITTER,
+            IdLayout::SONYFLAKE,
+            IdLayout::DISCORD,
+            IdLayout::INSTAGRAM,
+            IdLayout::MASTODON,
+        ] {
+            // Mastodon ids have no machine id
+            let machine_id = layout.max_machine_id().min(5);
+
+            let worker = FastIdWorker::builder()
+                .clock(MockClock::new(now))
+                .layout(layout)
+                .machine_id(machine_id)
+                .build()
+                .unwrap();
+
+            worker.next_id();
+            let parts = worker.decode(&worker.next_id());
+
+            assert_eq!(parts.timestamp, layout.timestamp_at(layout.ticks_at(now)));
+            assert!(now.duration_since(parts.timestamp).unwrap() < layout.tick().as_duration());
+            assert_eq!(parts.sequence, 1);
+            assert_eq!(parts.machine_id, machine_id);
+        }
+    }
+}
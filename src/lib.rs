@@ -1,12 +1,48 @@
-use std::ops::Add;
-use std::sync::Mutex;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+mod builder;
+mod cipher;
+mod clock;
+mod encoding;
+mod error;
+#[cfg(feature = "guid")]
+mod guid;
+mod layout;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod sharded;
+mod typed;
+
+pub use builder::FastIdWorkerBuilder;
+pub use cipher::FastIdCipher;
+pub use clock::{Clock, MockClock, MonotonicClock, SystemClock};
+use encoding::Alphabet;
+pub use error::{FastIdError, ParseFastIdError, TryFromIntError};
+#[cfg(feature = "guid")]
+pub use guid::GuidFormat;
+pub use layout::{FieldOrder, IdLayout, TickResolution};
+pub use sharded::{FastIdShard, ShardedFastIdWorker};
+pub use typed::{IdPrefix, PrefixStyle, TypedFastId};
 
 pub const DEFAULT_EPOCH: u64 = 1527811200000000000;
 
-pub struct FastId(i64, #[cfg(feature = "guid")] uuid::Uuid);
+/// An id generated by a [`FastIdWorker`].
+///
+/// Ids compare, hash and order by their numeric value, so ids generated by
+/// one worker are ordered as they were generated, and ids from different
+/// workers are ordered by their timestamp first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FastId(i64);
 
 impl FastId {
+    /// Wraps an id that is known to be non-negative.
+    fn from_raw(id: i64) -> Self {
+        debug_assert!(id >= 0);
+
+        FastId(id)
+    }
+
     pub fn as_i64(&self) -> i64 {
         self.0
     }
@@ -15,23 +51,287 @@ impl FastId {
         self.0 as u64
     }
 
+    /// Returns the GUID of the given format for an id with the given layout,
+    /// see [`IdLayout::to_guid`].
     #[cfg(feature = "guid")]
-    pub fn as_guid(&self) -> uuid::Uuid {
-        self.1
+    pub fn to_guid(
+        &self,
+        layout: &IdLayout,
+        format: GuidFormat,
+    ) -> Result<uuid::Uuid, FastIdError> {
+        layout.to_guid(self, format)
+    }
+
+    /// Returns the 13 characters of the id in
+    /// [Crockford's base32](https://www.crockford.com/base32.html), as used by
+    /// ULIDs, which sort like the id.
+    ///
+    /// ```
+    /// # use fastid::FastId;
+    /// let id = FastId::try_from(1_234_567_890_123_456_789i64).unwrap();
+    /// assert_eq!(id.to_base32(), "128GGYHYYK08N");
+    /// ```
+    pub fn to_base32(&self) -> String {
+        Alphabet::CROCKFORD.encode(self.as_u64())
+    }
+
+    /// Parses the 13 characters produced by [`FastId::to_base32`], in any case
+    /// and with I and L read as 1 and O read as 0.
+    ///
+    /// ```
+    /// # use fastid::FastId;
+    /// let id = FastId::from_base32("128ggyhyyk08n").unwrap();
+    /// assert_eq!(id.as_i64(), 1_234_567_890_123_456_789);
+    /// ```
+    pub fn from_base32(s: &str) -> Result<Self, ParseFastIdError> {
+        FastId::from_encoding(&Alphabet::CROCKFORD, s)
+    }
+
+    /// Returns the 13 characters of [`FastId::to_base32`] followed by the
+    /// Crockford mod-37 check symbol, for ids read out or typed by people.
+    ///
+    /// With `grouped` the 14 symbols are split into groups of five by
+    /// hyphens.
+    ///
+    /// ```
+    /// # use fastid::FastId;
+    /// let id = FastId::try_from(1_234_567_890_123_456_789i64).unwrap();
+    /// assert_eq!(id.to_base32_check(true), "128GG-YHYYK-08NT");
+    /// ```
+    pub fn to_base32_check(&self, grouped: bool) -> String {
+        encoding::encode_checked(self.as_u64(), grouped)
+    }
+
+    /// Parses the symbols produced by [`FastId::to_base32_check`], ignoring
+    /// case and hyphens and reading I and L as 1 and O as 0.
+    pub fn from_base32_check(s: &str) -> Result<Self, ParseFastIdError> {
+        i64::try_from(encoding::decode_checked(s)?)
+            .map(FastId::from_raw)
+            .map_err(|_| ParseFastIdError::Overflow)
+    }
+
+    /// Returns the 11 characters of the id in base62 with digits before upper
+    /// case before lower case letters, which sort like the id.
+    pub fn to_sortable_base62(&self) -> String {
+        Alphabet::SORTABLE_BASE62.encode(self.as_u64())
+    }
+
+    /// Parses the 11 characters produced by [`FastId::to_sortable_base62`].
+    pub fn from_sortable_base62(s: &str) -> Result<Self, ParseFastIdError> {
+        FastId::from_encoding(&Alphabet::SORTABLE_BASE62, s)
+    }
+
+    /// Returns the 11 characters of the big-endian bytes of the id in base64
+    /// with the URL-safe characters in ASCII order, `-0-9A-Z_a-z`, which sort
+    /// like the id.
+    pub fn to_sortable_base64(&self) -> String {
+        Alphabet::SORTABLE_BASE64.encode(self.as_u64())
+    }
+
+    /// Parses the 11 characters produced by [`FastId::to_sortable_base64`].
+    pub fn from_sortable_base64(s: &str) -> Result<Self, ParseFastIdError> {
+        FastId::from_encoding(&Alphabet::SORTABLE_BASE64, s)
     }
 
-    #[cfg(feature = "base62")]
+    fn from_encoding(alphabet: &Alphabet, s: &str) -> Result<Self, ParseFastIdError> {
+        i64::try_from(alphabet.decode(s)?)
+            .map(FastId::from_raw)
+            .map_err(|_| ParseFastIdError::Overflow)
+    }
+
+    /// Returns the same 11 characters as [`FastId::to_sortable_base62`].
+    #[deprecated(note = "use `FastId::to_sortable_base62`, which is the same encoding")]
     pub fn to_base62(&self) -> String {
-        format!("{:0>11}", base62::encode(self.as_u64()))
+        self.to_sortable_base62()
+    }
+
+    /// Parses the 11 characters produced by [`FastId::to_base62`].
+    #[deprecated(note = "use `FastId::from_sortable_base62`, which is the same encoding")]
+    pub fn from_base62(s: &str) -> Result<Self, ParseFastIdError> {
+        FastId::from_sortable_base62(s)
     }
 
+    /// Returns the 11 characters of the big-endian bytes of the id in
+    /// unpadded URL-safe base64.
+    ///
+    /// ```
+    /// # use fastid::FastId;
+    /// let id = FastId::try_from(1_234_567_890_123_456_789i64).unwrap();
+    /// assert_eq!(id.to_base64(), "ESIQ9H3pgRU");
+    /// ```
     #[cfg(feature = "base64")]
     pub fn to_base64(&self) -> String {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+
+        URL_SAFE_NO_PAD.encode(u64::to_be_bytes(self.as_u64()))
+    }
+
+    /// Parses the 11 characters produced by [`FastId::to_base64`], rejecting
+    /// strings whose last character has bits set beyond the 64 of the id.
+    #[cfg(feature = "base64")]
+    pub fn from_base64(s: &str) -> Result<Self, ParseFastIdError> {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, DecodeError, Engine as _};
+
+        if s.len() != 11 {
+            return Err(ParseFastIdError::InvalidLength {
+                expected: 11,
+                found: s.len(),
+            });
+        }
+
+        let bytes = URL_SAFE_NO_PAD.decode(s).map_err(|e| match e {
+            DecodeError::InvalidByte(index, byte) | DecodeError::InvalidLastSymbol(index, byte) => {
+                ParseFastIdError::InvalidCharacter {
+                    character: invalid_char(s, byte, index),
+                    index,
+                }
+            }
+            // 11 characters without padding always decode to 8 bytes
+            DecodeError::InvalidLength | DecodeError::InvalidPadding => {
+                let index = s.find('=').unwrap_or(10);
+
+                ParseFastIdError::InvalidCharacter {
+                    character: invalid_char(s, s.as_bytes()[index], index),
+                    index,
+                }
+            }
+        })?;
+        let bytes: [u8; 8] = bytes.try_into().expect("11 characters decode to 8 bytes");
+
+        i64::try_from(u64::from_be_bytes(bytes))
+            .map(FastId::from_raw)
+            .map_err(|_| ParseFastIdError::Overflow)
+    }
+
+    /// Returns the 12 characters of the little-endian bytes of the id in
+    /// standard base64, left-padded with `0`, as [`FastId::to_base64`] did
+    /// before it switched to URL-safe base64.
+    #[cfg(feature = "base64")]
+    pub fn to_legacy_base64(&self) -> String {
         use base64::{engine::general_purpose::STANDARD, Engine as _};
 
         let bytes = u64::to_le_bytes(self.as_u64());
         format!("{:0>12}", STANDARD.encode(bytes))
     }
+
+    /// Parses the 12 characters produced by [`FastId::to_legacy_base64`].
+    #[cfg(feature = "base64")]
+    pub fn from_legacy_base64(s: &str) -> Result<Self, ParseFastIdError> {
+        use base64::{engine::general_purpose::STANDARD, DecodeError, Engine as _};
+
+        if s.len() != 12 {
+            return Err(ParseFastIdError::InvalidLength {
+                expected: 12,
+                found: s.len(),
+            });
+        }
+
+        let bytes = STANDARD.decode(s).map_err(|e| match e {
+            DecodeError::InvalidByte(index, byte) | DecodeError::InvalidLastSymbol(index, byte) => {
+                ParseFastIdError::InvalidCharacter {
+                    character: invalid_char(s, byte, index),
+                    index,
+                }
+            }
+            // 12 characters decode to 9 bytes without the trailing padding
+            DecodeError::InvalidLength | DecodeError::InvalidPadding => {
+                ParseFastIdError::InvalidCharacter {
+                    character: invalid_char(s, s.as_bytes()[11], 11),
+                    index: 11,
+                }
+            }
+        })?;
+        let bytes: [u8; 8] = bytes
+            .try_into()
+            .map_err(|_| ParseFastIdError::InvalidCharacter {
+                character: invalid_char(s, s.as_bytes()[11], 11),
+                index: 11,
+            })?;
+
+        i64::try_from(u64::from_le_bytes(bytes))
+            .map(FastId::from_raw)
+            .map_err(|_| ParseFastIdError::Overflow)
+    }
+}
+
+/// Returns the character starting at `index`, or the byte itself if `index`
+/// falls inside a multi-byte character.
+fn invalid_char(s: &str, byte: u8, index: usize) -> char {
+    s.get(index..)
+        .and_then(|rest| rest.chars().next())
+        .unwrap_or(byte as char)
+}
+
+impl std::str::FromStr for FastId {
+    type Err = ParseFastIdError;
+
+    /// Parses the decimal form produced by [`Display`](std::fmt::Display).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseFastIdError::InvalidLength {
+                expected: 1,
+                found: 0,
+            });
+        }
+
+        let mut id: i64 = 0;
+        for (index, character) in s.char_indices() {
+            let digit = character
+                .to_digit(10)
+                .ok_or(ParseFastIdError::InvalidCharacter { character, index })?;
+
+            id = id
+                .checked_mul(10)
+                .and_then(|id| id.checked_add(digit as i64))
+                .ok_or(ParseFastIdError::Overflow)?;
+        }
+
+        Ok(FastId::from_raw(id))
+    }
+}
+
+/// The components packed into a [`FastId`] by [`FastIdWorker::next_id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FastIdParts {
+    pub timestamp: SystemTime,
+    pub sequence: u64,
+    pub machine_id: u64,
+}
+
+impl From<FastId> for i64 {
+    fn from(id: FastId) -> Self {
+        id.as_i64()
+    }
+}
+
+impl From<FastId> for u64 {
+    fn from(id: FastId) -> Self {
+        id.as_u64()
+    }
+}
+
+impl TryFrom<i64> for FastId {
+    type Error = TryFromIntError;
+
+    /// Fails for negative numbers, which no worker generates.
+    fn try_from(id: i64) -> Result<Self, Self::Error> {
+        if id < 0 {
+            return Err(TryFromIntError(()));
+        }
+
+        Ok(FastId::from_raw(id))
+    }
+}
+
+impl TryFrom<u64> for FastId {
+    type Error = TryFromIntError;
+
+    /// Fails for numbers that use the sign bit, which no worker generates.
+    fn try_from(id: u64) -> Result<Self, Self::Error> {
+        i64::try_from(id)
+            .map(FastId::from_raw)
+            .map_err(|_| TryFromIntError(()))
+    }
 }
 
 impl std::fmt::Binary for FastId {
@@ -40,43 +340,144 @@ impl std::fmt::Binary for FastId {
     }
 }
 
+impl std::fmt::Octal for FastId {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        self.0.fmt(fmt)
+    }
+}
+
+impl std::fmt::LowerHex for FastId {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        self.0.fmt(fmt)
+    }
+}
+
+impl std::fmt::UpperHex for FastId {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        self.0.fmt(fmt)
+    }
+}
+
 impl std::fmt::Display for FastId {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         self.0.fmt(fmt)
     }
 }
 
-#[derive(Debug)]
-pub struct FastIdWorker {
-    time_bits: usize,
-    machine_bits: usize,
-    sequence_bits: usize,
-    #[cfg(feature = "guid")]
-    placeholder_bits: usize,
+/// The consecutive ids generated by [`FastIdWorker::next_ids`], in increasing
+/// order.
+#[derive(Debug, Clone)]
+pub struct FastIdBatch {
+    layout: IdLayout,
+    machine_id: u64,
+    /// The timestamp of the next id shifted above its sequence, like the
+    /// state of the worker.
+    next: u64,
+    end: u64,
+}
+
+impl Iterator for FastIdBatch {
+    type Item = FastId;
+
+    fn next(&mut self) -> Option<FastId> {
+        if self.next == self.end {
+            return None;
+        }
+
+        let slot = self.next;
+        self.next += 1;
+
+        let ts = slot >> self.layout.sequence_bits();
+        let sequence = slot & self.layout.max_sequence();
+
+        Some(FastId::from_raw(
+            self.layout.pack(ts, sequence, self.machine_id) as i64,
+        ))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end - self.next) as usize;
+
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for FastIdBatch {}
+
+impl std::iter::FusedIterator for FastIdBatch {}
+
+/// What a [`FastIdWorker`] does when the clock reads earlier than the
+/// timestamp of the last id it generated, e.g. after an NTP step back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockRegression {
+    /// Keep generating ids with the last timestamp, as a logical clock, until
+    /// the clock catches up. The logical clock moves on to the next tick when
+    /// the sequence of the last one runs out, without waiting.
+    #[default]
+    Logical,
+    /// Block until the clock catches up with the last timestamp.
+    Wait,
+    /// Fail with [`FastIdError::ClockMovedBackwards`].
+    Fail,
+}
 
-    time_mask: u64,
-    machine_mask: u64,
-    sequence_mask: u64,
+/// What a [`FastIdWorker`] does when all sequence numbers of the current tick
+/// have been used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SequenceExhaustion {
+    /// Sleep until the next tick.
+    #[default]
+    Sleep,
+    /// Keep generating ids in the following ticks, running at most
+    /// `max_ticks` ahead of the clock before sleeping.
+    Borrow { max_ticks: u64 },
+    /// Fail with [`FastIdError::WouldBlock`].
+    Fail,
+}
+
+#[derive(Debug)]
+pub struct FastIdWorker<C = SystemClock> {
+    clock: C,
+    layout: IdLayout,
+    clock_regression: ClockRegression,
+    exhaustion: SequenceExhaustion,
     #[cfg(feature = "guid")]
-    placeholder_mask: u64,
+    guid_format: GuidFormat,
 
     machine_id: u64,
-    sequence: Mutex<u64>,
 
-    epoch: SystemTime,
+    /// The timestamp of the last id shifted above its sequence, so both are
+    /// updated together by a single compare-and-swap.
+    state: AtomicU64,
 
-    last_timestamp: Mutex<u64>,
+    exhaustion_warning: Option<ExhaustionWarning>,
+}
+
+#[derive(Debug)]
+struct ExhaustionWarning {
+    /// The first tick within the warning horizon.
+    from: u64,
+    callback: fn(SystemTime),
+    warned: AtomicBool,
 }
 
 impl FastIdWorker {
-    pub fn new(machine_id: u64) -> Self {
-        // time_bits: 40,
-        // machine_bits: 16,
-        // sequence_bits: 7,
+    /// Returns a builder that validates the configuration instead of panicking.
+    pub fn builder() -> FastIdWorkerBuilder {
+        FastIdWorkerBuilder::new()
+    }
 
-        FastIdWorker::with_bits(40, 16, 7, machine_id)
+    /// # Panics
+    ///
+    /// Panics if `machine_id` does not fit in 16 bits.
+    pub fn new(machine_id: u64) -> Self {
+        FastIdWorker::with_layout(IdLayout::default(), machine_id)
     }
 
+    /// # Panics
+    ///
+    /// Panics if the bits do not form a valid [`IdLayout`] or `machine_id`
+    /// does not fit in `machine_bits`, see [`FastIdWorker::builder`].
     pub fn with_bits(
         time_bits: usize,
         machine_bits: usize,
@@ -92,6 +493,10 @@ impl FastIdWorker {
         )
     }
 
+    /// # Panics
+    ///
+    /// Panics if the bits do not form a valid [`IdLayout`] or `machine_id`
+    /// does not fit in `machine_bits`, see [`FastIdWorker::builder`].
     pub fn with_bits_and_epoch(
         time_bits: usize,
         machine_bits: usize,
@@ -99,99 +504,263 @@ impl FastIdWorker {
         machine_id: u64,
         timestamp: u64,
     ) -> Self {
-        let max = u64::MAX;
+        let layout = IdLayout::new(time_bits, machine_bits, sequence_bits)
+            .unwrap_or_else(|e| panic!("invalid worker configuration: {}", e))
+            .with_epoch(timestamp);
 
-        let time_mask = !(max << time_bits);
-        let machine_mask = !(max << machine_bits);
-        let sequence_mask = !(max << sequence_bits);
+        FastIdWorker::with_layout(layout, machine_id)
+    }
 
-        #[cfg(feature = "guid")]
-        let placeholder_bits = 14 - sequence_bits;
-        #[cfg(feature = "guid")]
-        let placeholder_mask = !(max << placeholder_bits);
+    /// # Panics
+    ///
+    /// Panics if `machine_id` does not fit in the layout's machine field, see
+    /// [`FastIdWorker::builder`].
+    pub fn with_layout(layout: IdLayout, machine_id: u64) -> Self {
+        FastIdWorker::builder()
+            .layout(layout)
+            .machine_id(machine_id)
+            .build()
+            .unwrap_or_else(|e| panic!("invalid worker configuration: {}", e))
+    }
+}
 
-        let epoch = UNIX_EPOCH.add(Duration::from_nanos(timestamp));
+impl<C: Clock> FastIdWorker<C> {
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
 
-        FastIdWorker {
-            time_bits,
-            machine_bits,
-            sequence_bits,
-            #[cfg(feature = "guid")]
-            placeholder_bits,
+    pub fn layout(&self) -> &IdLayout {
+        &self.layout
+    }
 
-            time_mask,
-            machine_mask,
-            sequence_mask,
-            #[cfg(feature = "guid")]
-            placeholder_mask,
+    fn get_current_timestamp(&self) -> u64 {
+        self.layout.ticks_at(self.clock.now())
+    }
 
-            machine_id,
-            sequence: Mutex::new(0),
+    /// Decodes an id produced by a worker with the same layout back into the
+    /// instant it was generated at, its sequence and its machine id.
+    ///
+    /// See [`IdLayout::decode`].
+    pub fn decode(&self, id: &FastId) -> FastIdParts {
+        self.layout.decode(id)
+    }
 
-            epoch: epoch,
+    /// Generates the next id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the worker was built with [`ClockRegression::Fail`] or
+    /// [`SequenceExhaustion::Fail`] and could not generate an id without
+    /// waiting, see [`FastIdWorker::try_next_id`].
+    pub fn next_id(&self) -> FastId {
+        self.try_next_id()
+            .unwrap_or_else(|e| panic!("failed to generate id: {}", e))
+    }
 
-            last_timestamp: Mutex::new(0),
-        }
+    /// Returns the GUID of an id generated by this worker, in the format the
+    /// worker was built with.
+    ///
+    /// GUIDs are only built on demand, so generating ids costs the same with
+    /// or without the `guid` feature.
+    #[cfg(feature = "guid")]
+    pub fn to_guid(&self, id: &FastId) -> Result<uuid::Uuid, FastIdError> {
+        self.layout.to_guid(id, self.guid_format)
     }
 
-    fn get_current_timestamp(&self) -> u64 {
-        let duration = SystemTime::now()
-            .duration_since(self.epoch)
-            .unwrap_or(Duration::new(0, 0));
+    /// Returns the id a GUID returned by [`FastIdWorker::to_guid`] was built
+    /// from, see [`IdLayout::from_guid`].
+    #[cfg(feature = "guid")]
+    pub fn from_guid(&self, guid: &uuid::Uuid) -> Result<FastId, FastIdError> {
+        self.layout.from_guid(guid, self.guid_format)
+    }
 
-        let timestamp = duration.as_nanos() >> 20;
+    /// Returns the instant after which the worker cannot generate ids anymore,
+    /// see [`IdLayout::expires_at`].
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.layout.expires_at()
+    }
 
-        timestamp as u64
+    /// Returns how long the worker can keep generating ids for, according to
+    /// its clock.
+    pub fn remaining_lifetime(&self) -> Option<Duration> {
+        let expires_at = self.expires_at()?;
+
+        Some(
+            expires_at
+                .duration_since(self.clock.now())
+                .unwrap_or(Duration::new(0, 0)),
+        )
     }
 
-    pub fn next_id(&self) -> FastId {
-        loop {
-            let ts = self.get_current_timestamp();
-
-            let mut last_timestamp = self.last_timestamp.lock().unwrap();
-            let mut sequence = self.sequence.lock().unwrap();
-
-            if ts > *last_timestamp {
-                *last_timestamp = ts;
-                *sequence = 0
-            } else if *sequence >= self.sequence_mask {
-                continue;
-            } else {
-                *sequence += 1;
-            }
+    /// Generates the next id, failing instead of panicking when the worker was
+    /// built with [`ClockRegression::Fail`] and the clock moved backwards, or
+    /// with [`SequenceExhaustion::Fail`] and the sequence of the current tick
+    /// is exhausted.
+    ///
+    /// Always fails with [`FastIdError::TimeExhausted`] once the clock is past
+    /// [`FastIdWorker::expires_at`], rather than wrapping the time field.
+    pub fn try_next_id(&self) -> Result<FastId, FastIdError> {
+        let slot = self.claim(1)?;
+        let (ts, sequence) = self.unpack_state(slot);
 
-            let id = ((ts & self.time_mask) << (self.machine_bits + self.sequence_bits))
-                | ((*sequence & self.sequence_mask) << self.machine_bits)
-                | (self.machine_id & self.machine_mask);
-            let id = id as i64;
+        Ok(self.make_id(ts, sequence))
+    }
 
-            #[cfg(feature = "guid")]
-            {
-                // codes from https://github.com/uuid-rs/uuid/blob/805f4edd4d356dc05b5be55397f7fb43e47a78eb/src/v1.rs#L195-L216
+    /// Generates `n` consecutive ids with a single update of the worker, for
+    /// bulk inserts.
+    ///
+    /// The ids are strictly increasing and no other call generates an id
+    /// between them. A batch larger than the sequence of a tick spans several
+    /// ticks, waiting for the clock like [`FastIdWorker::next_id`] whenever it
+    /// would get further ahead than the [`SequenceExhaustion`] allows.
+    ///
+    /// ```
+    /// use fastid::FastIdWorker;
+    ///
+    /// let worker = FastIdWorker::new(1);
+    /// let ids: Vec<_> = worker.next_ids(500).collect();
+    ///
+    /// assert_eq!(ids.len(), 500);
+    /// assert!(ids.windows(2).all(|w| w[0] < w[1]));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics where [`FastIdWorker::next_id`] does, see
+    /// [`FastIdWorker::try_next_ids`].
+    pub fn next_ids(&self, n: u64) -> FastIdBatch {
+        self.try_next_ids(n)
+            .unwrap_or_else(|e| panic!("failed to generate ids: {}", e))
+    }
+
+    /// Generates `n` consecutive ids like [`FastIdWorker::next_ids`], failing
+    /// where [`FastIdWorker::try_next_id`] does.
+    ///
+    /// With [`SequenceExhaustion::Fail`] the batch has to fit in the current
+    /// tick, otherwise [`FastIdError::WouldBlock`] tells when it will.
+    pub fn try_next_ids(&self, n: u64) -> Result<FastIdBatch, FastIdError> {
+        let first = if n == 0 { 0 } else { self.claim(n)? };
+
+        Ok(FastIdBatch {
+            layout: self.layout,
+            machine_id: self.machine_id,
+            next: first,
+            end: first + n,
+        })
+    }
 
-                let time_low = (ts & 0xFFFF_FFFF) as u32;
-                let time_mid = ((ts >> 32) & 0xFFFF) as u16;
-                let time_high_and_version = (((ts >> 48) & 0x0FFF) as u16) | (1 << 12);
+    /// Claims the `n` timestamps and sequences following the last id, as
+    /// consecutive values of `state`, and returns the first.
+    fn claim(&self, n: u64) -> Result<u64, FastIdError> {
+        let lookahead = match self.exhaustion {
+            SequenceExhaustion::Borrow { max_ticks } => max_ticks,
+            _ => 0,
+        };
 
-                let mut d4 = [0; 8];
+        // waiting for later ticks must not push the start of the run back, so
+        // it is taken from the first reading of the clock only
+        let mut start = None;
 
-                let sequence = (*sequence << self.placeholder_bits) | (ts & self.placeholder_mask);
+        loop {
+            // the clock is read after the state, so a tick stored by another
+            // thread in between is not mistaken for the clock moving back
+            let state = self.state.load(Ordering::Acquire);
+            let (last_timestamp, _) = self.unpack_state(state);
 
-                d4[0] = (((sequence & 0x3F00) >> 8) as u8) | 0x80;
-                d4[1] = (sequence & 0xFF) as u8;
+            let now = self.get_current_timestamp();
+            let start = *start.get_or_insert_with(|| self.pack_state(now, 0));
 
-                let node_id = u64::to_be_bytes(self.machine_id & 0xFFFF_FFFF_FFFF);
-                d4[2..].copy_from_slice(&node_id[2..]);
+            let behind = now.saturating_add(lookahead) < last_timestamp;
+            if behind {
+                let by = self.ticks_to_duration(last_timestamp - now);
 
-                let guid = uuid::Uuid::from_fields(time_low, time_mid, time_high_and_version, &d4);
+                match self.clock_regression {
+                    // keeps using the sequence of the last tick
+                    ClockRegression::Logical => {}
+                    ClockRegression::Wait => {
+                        self.clock
+                            .sleep(self.duration_until(last_timestamp - lookahead));
+                        continue;
+                    }
+                    ClockRegression::Fail => return Err(FastIdError::ClockMovedBackwards { by }),
+                }
+            }
 
-                return FastId(id, guid);
+            if now > self.layout.max_ticks() {
+                return Err(FastIdError::TimeExhausted);
             }
 
-            #[cfg(not(feature = "guid"))]
-            return FastId(id);
+            let first = (state + 1).max(start);
+            let last = first.checked_add(n - 1).ok_or(FastIdError::TimeExhausted)?;
+            let (last_tick, _) = self.unpack_state(last);
+
+            if last_tick > self.layout.max_ticks() {
+                return Err(FastIdError::TimeExhausted);
+            }
+
+            // ids may run ahead of the clock by the lookahead, and follow the
+            // logical clock while the clock is behind it
+            if !behind && last_tick > now.saturating_add(lookahead) {
+                // the sequence of the last tick that can be used is exhausted
+                let wait_until = last_tick - lookahead;
+
+                match self.exhaustion {
+                    SequenceExhaustion::Sleep | SequenceExhaustion::Borrow { .. } => {
+                        self.clock.sleep(self.duration_until(wait_until));
+                        continue;
+                    }
+                    SequenceExhaustion::Fail => {
+                        return Err(FastIdError::WouldBlock {
+                            retry_after: self.duration_until(wait_until),
+                        })
+                    }
+                }
+            }
+
+            if self
+                .state
+                .compare_exchange_weak(state, last, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                if let Some(warning) = &self.exhaustion_warning {
+                    if last_tick >= warning.from && !warning.warned.swap(true, Ordering::Relaxed) {
+                        if let Some(expires_at) = self.expires_at() {
+                            (warning.callback)(expires_at);
+                        }
+                    }
+                }
+
+                return Ok(first);
+            }
         }
     }
+
+    fn ticks_to_duration(&self, ticks: u64) -> Duration {
+        Duration::from_nanos(ticks.saturating_mul(self.layout.tick().as_nanos()))
+    }
+
+    /// Returns how long it is until the given tick starts on the worker's clock.
+    fn duration_until(&self, ticks: u64) -> Duration {
+        self.layout
+            .timestamp_at(ticks)
+            .duration_since(self.clock.now())
+            .unwrap_or(Duration::new(0, 0))
+    }
+
+    fn pack_state(&self, ts: u64, sequence: u64) -> u64 {
+        (ts << self.layout.sequence_bits()) | sequence
+    }
+
+    fn unpack_state(&self, state: u64) -> (u64, u64) {
+        (
+            state >> self.layout.sequence_bits(),
+            state & self.layout.max_sequence(),
+        )
+    }
+
+    fn make_id(&self, ts: u64, sequence: u64) -> FastId {
+        FastId(self.layout.pack(ts, sequence, self.machine_id) as i64)
+    }
 }
 
 #[cfg(test)]
@@ -200,7 +769,7 @@ mod tests {
 
     #[test]
     fn can_generate_id() {
-        let mut worker = FastIdWorker::new(u64::MAX);
+        let worker = FastIdWorker::new(0xFFFF);
         let id = worker.next_id();
 
         assert_eq!(format!("{:#064b}", id), format!("{:#064b}", id.as_i64()));
@@ -209,7 +778,7 @@ mod tests {
 
     #[test]
     fn can_generate_many_ids() {
-        let mut worker = FastIdWorker::new(u64::MAX);
+        let worker = FastIdWorker::new(0xFFFF);
 
         let mut last_id = worker.next_id();
         for _ in 0..1000 {
@@ -218,4 +787,620 @@ mod tests {
             last_id = id;
         }
     }
+
+    #[test]
+    fn can_decode_id() {
+        let worker = FastIdWorker::new(42);
+
+        let before = SystemTime::now();
+        let first = worker.next_id();
+        let second = worker.next_id();
+        let after = SystemTime::now();
+
+        let parts = worker.decode(&first);
+        assert_eq!(parts.machine_id, 42);
+        assert!(parts.timestamp <= after);
+        assert!(parts.timestamp + TickResolution::DEFAULT.as_duration() > before);
+
+        let next = worker.decode(&second);
+        assert_eq!(next.machine_id, 42);
+        if next.timestamp == parts.timestamp {
+            assert_eq!(next.sequence, parts.sequence + 1);
+        } else {
+            assert_eq!(next.sequence, 0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "machine id 65536 is larger than the maximum of 65535")]
+    fn rejects_truncated_machine_id() {
+        FastIdWorker::new(0x1_0000);
+    }
+
+    #[test]
+    #[should_panic(expected = "layout needs 64 bits")]
+    fn rejects_oversized_layout() {
+        FastIdWorker::with_bits(64, 0, 0, 0);
+    }
+
+    fn worker_with(clock_regression: ClockRegression) -> (FastIdWorker<MockClock>, MockClock) {
+        worker_exhausting(clock_regression, SequenceExhaustion::Sleep)
+    }
+
+    fn worker_exhausting(
+        clock_regression: ClockRegression,
+        exhaustion: SequenceExhaustion,
+    ) -> (FastIdWorker<MockClock>, MockClock) {
+        let clock = MockClock::new(IdLayout::default().timestamp_at(10));
+        let worker = FastIdWorker::builder()
+            .clock(clock.clone())
+            .machine_id(1)
+            .clock_regression(clock_regression)
+            .sequence_exhaustion(exhaustion)
+            .build()
+            .unwrap();
+
+        (worker, clock)
+    }
+
+    #[test]
+    fn waits_for_next_tick_when_sequence_is_exhausted() {
+        let (worker, _) = worker_with(ClockRegression::Logical);
+        let max = worker.layout().max_sequence();
+
+        let ids: Vec<_> = (0..=max + 1).map(|_| worker.next_id()).collect();
+
+        assert!(ids.windows(2).all(|w| w[0].as_i64() < w[1].as_i64()));
+        assert_eq!(worker.decode(&ids[max as usize]).sequence, max);
+
+        let rolled_over = worker.decode(&ids[max as usize + 1]);
+        assert_eq!(rolled_over.timestamp, worker.layout().timestamp_at(11));
+        assert_eq!(rolled_over.sequence, 0);
+    }
+
+    #[test]
+    fn keeps_last_timestamp_when_clock_moves_backwards() {
+        let (worker, clock) = worker_with(ClockRegression::Logical);
+        let tick = worker.layout().tick().as_duration();
+
+        let mut ids = vec![worker.next_id(), worker.next_id()];
+        clock.rewind(tick * 5);
+        ids.push(worker.next_id());
+        clock.advance(tick * 6);
+        ids.push(worker.next_id());
+
+        let parts: Vec<_> = ids.iter().map(|id| worker.decode(id)).collect();
+
+        assert!(ids.windows(2).all(|w| w[0].as_i64() < w[1].as_i64()));
+        assert_eq!(parts[2].timestamp, parts[1].timestamp);
+        assert_eq!(parts[2].sequence, 2);
+        assert_eq!(parts[3].timestamp, worker.layout().timestamp_at(11));
+        assert_eq!(parts[3].sequence, 0);
+    }
+
+    #[test]
+    fn advances_logical_clock_while_clock_is_behind() {
+        let (worker, clock) = worker_with(ClockRegression::Logical);
+        let per_tick = worker.layout().max_sequence() + 1;
+
+        let first = worker.next_id();
+        clock.rewind(Duration::from_secs(3600));
+        let rewound = clock.now();
+
+        let ids: Vec<_> = (0..per_tick * 2).map(|_| worker.next_id()).collect();
+        assert!(ids[0] > first);
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(clock.now(), rewound);
+
+        let last = worker.decode(ids.last().unwrap());
+        assert_eq!(last.timestamp, worker.layout().timestamp_at(12));
+        assert_eq!(last.sequence, 0);
+    }
+
+    #[test]
+    fn waits_when_clock_moves_backwards() {
+        let (worker, clock) = worker_with(ClockRegression::Wait);
+        let tick = worker.layout().tick().as_duration();
+
+        let first = worker.next_id();
+        clock.rewind(tick * 5);
+        let second = worker.next_id();
+
+        assert!(second.as_i64() > first.as_i64());
+        assert_eq!(worker.decode(&second).sequence, 1);
+        assert_eq!(clock.now(), worker.layout().timestamp_at(10));
+    }
+
+    #[test]
+    fn fails_when_clock_moves_backwards() {
+        let (worker, clock) = worker_with(ClockRegression::Fail);
+        let tick = worker.layout().tick().as_duration();
+
+        worker.next_id();
+        clock.rewind(tick * 3);
+        assert_eq!(
+            worker.try_next_id().err(),
+            Some(FastIdError::ClockMovedBackwards { by: tick * 3 })
+        );
+
+        clock.advance(tick * 3);
+        assert!(worker.try_next_id().is_ok());
+    }
+
+    /// Runs the jobs made by `job` on 4 threads and asserts that the ids they
+    /// return are unique.
+    pub(crate) fn assert_unique_across_threads<F>(mut job: impl FnMut() -> F)
+    where
+        F: FnOnce() -> Vec<FastId> + Send,
+    {
+        let mut ids: Vec<FastId> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4).map(|_| scope.spawn(job())).collect();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+        let len = ids.len();
+
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), len);
+    }
+
+    #[test]
+    fn generates_unique_ids_across_threads() {
+        let worker = &FastIdWorker::new(1);
+
+        assert_unique_across_threads(|| move || (0..1000).map(|_| worker.next_id()).collect());
+    }
+
+    #[test]
+    fn does_not_mistake_other_threads_for_clock_regressions() {
+        /// Returns readings of the system clock late, so other threads are
+        /// likely to move to a later tick in the meantime.
+        struct LateClock;
+
+        impl Clock for LateClock {
+            fn now(&self) -> SystemTime {
+                let now = SystemTime::now();
+                std::thread::sleep(Duration::from_micros(100));
+                now
+            }
+        }
+
+        let worker = FastIdWorker::builder()
+            .clock(LateClock)
+            .clock_regression(ClockRegression::Fail)
+            .build()
+            .unwrap();
+
+        let errors: usize = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| (0..1000).filter(|_| worker.try_next_id().is_err()).count())
+                })
+                .collect();
+
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+
+        assert_eq!(errors, 0);
+    }
+
+    #[test]
+    fn fails_when_sequence_is_exhausted() {
+        let (worker, clock) = worker_exhausting(ClockRegression::Logical, SequenceExhaustion::Fail);
+        let tick = worker.layout().tick().as_duration();
+
+        for _ in 0..=worker.layout().max_sequence() {
+            worker.try_next_id().unwrap();
+        }
+
+        assert_eq!(
+            worker.try_next_id().err(),
+            Some(FastIdError::WouldBlock { retry_after: tick })
+        );
+
+        clock.advance(tick);
+        assert_eq!(worker.decode(&worker.next_id()).sequence, 0);
+    }
+
+    #[test]
+    fn borrows_ticks_when_sequence_is_exhausted() {
+        let (worker, clock) = worker_exhausting(
+            ClockRegression::Fail,
+            SequenceExhaustion::Borrow { max_ticks: 2 },
+        );
+        let per_tick = worker.layout().max_sequence() + 1;
+        let start = clock.now();
+
+        let ids: Vec<_> = (0..per_tick * 3).map(|_| worker.next_id()).collect();
+        assert!(ids.windows(2).all(|w| w[0].as_i64() < w[1].as_i64()));
+        assert_eq!(clock.now(), start);
+        assert_eq!(
+            worker.decode(ids.last().unwrap()).timestamp,
+            worker.layout().timestamp_at(12)
+        );
+
+        // running further ahead has to wait for the clock
+        let id = worker.next_id();
+        assert_eq!(
+            worker.decode(&id).timestamp,
+            worker.layout().timestamp_at(13)
+        );
+        assert_eq!(clock.now(), worker.layout().timestamp_at(11));
+
+        // being ahead by the borrowed ticks is not a clock regression
+        assert!(worker.try_next_id().is_ok());
+    }
+
+    #[test]
+    fn reserves_consecutive_ids_across_ticks() {
+        let (worker, clock) = worker_with(ClockRegression::Logical);
+        let per_tick = worker.layout().max_sequence() + 1;
+
+        let before = worker.next_id();
+        let batch = worker.next_ids(per_tick * 2 + 1);
+        assert_eq!(batch.len() as u64, per_tick * 2 + 1);
+
+        let ids: Vec<_> = batch.collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(worker.decode(&ids[0]).sequence, 1);
+        assert!(ids[0] > before);
+
+        // the run ends in the second tick after the first id, and has to wait
+        // for it to start
+        let last = worker.decode(ids.last().unwrap());
+        assert_eq!(last.timestamp, worker.layout().timestamp_at(12));
+        assert_eq!(last.sequence, 1);
+        assert_eq!(clock.now(), worker.layout().timestamp_at(12));
+
+        assert!(worker.next_id() > *ids.last().unwrap());
+        assert_eq!(worker.next_ids(0).len(), 0);
+    }
+
+    #[test]
+    fn fails_to_reserve_beyond_the_current_tick() {
+        let (worker, clock) = worker_exhausting(ClockRegression::Logical, SequenceExhaustion::Fail);
+        let tick = worker.layout().tick().as_duration();
+        let per_tick = worker.layout().max_sequence() + 1;
+
+        assert_eq!(
+            worker.try_next_ids(per_tick + 1).err(),
+            Some(FastIdError::WouldBlock { retry_after: tick })
+        );
+        assert_eq!(worker.try_next_ids(per_tick).map(|ids| ids.len()), Ok(128));
+        assert!(worker.try_next_id().is_err());
+
+        clock.advance(tick);
+        assert_eq!(worker.decode(&worker.next_id()).sequence, 0);
+    }
+
+    #[test]
+    fn reserves_unique_ids_across_threads() {
+        let worker = &FastIdWorker::new(1);
+
+        assert_unique_across_threads(|| {
+            move || {
+                (0..100)
+                    .flat_map(|_| worker.next_ids(10))
+                    .chain((0..100).map(|_| worker.next_id()))
+                    .collect()
+            }
+        });
+    }
+
+    #[test]
+    fn can_parse_decimal() {
+        let id = FastIdWorker::new(1).next_id();
+
+        let parsed: FastId = id.to_string().parse().unwrap();
+        assert_eq!(parsed.as_i64(), id.as_i64());
+
+        assert_eq!(
+            "".parse::<FastId>().err(),
+            Some(ParseFastIdError::InvalidLength {
+                expected: 1,
+                found: 0
+            })
+        );
+        assert_eq!(
+            "-1".parse::<FastId>().err(),
+            Some(ParseFastIdError::InvalidCharacter {
+                character: '-',
+                index: 0
+            })
+        );
+        assert_eq!(
+            "9223372036854775808".parse::<FastId>().err(),
+            Some(ParseFastIdError::Overflow)
+        );
+    }
+
+    #[allow(deprecated)]
+    #[test]
+    fn can_parse_base62() {
+        let id = FastIdWorker::new(1).next_id();
+
+        let parsed = FastId::from_base62(&id.to_base62()).unwrap();
+        assert_eq!(parsed.as_i64(), id.as_i64());
+
+        assert_eq!(
+            FastId::from_base62("0000000001").err(),
+            Some(ParseFastIdError::InvalidLength {
+                expected: 11,
+                found: 10
+            })
+        );
+        assert_eq!(
+            FastId::from_base62("000000000-1").err(),
+            Some(ParseFastIdError::InvalidCharacter {
+                character: '-',
+                index: 9
+            })
+        );
+        assert_eq!(
+            FastId::from_base62("zzzzzzzzzzz").err(),
+            Some(ParseFastIdError::Overflow)
+        );
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn can_parse_base64() {
+        let id = FastIdWorker::new(1).next_id();
+
+        let parsed = FastId::from_base64(&id.to_base64()).unwrap();
+        assert_eq!(parsed.as_i64(), id.as_i64());
+
+        assert_eq!(
+            FastId::from_base64("AAAAAAAAAAAA").err(),
+            Some(ParseFastIdError::InvalidLength {
+                expected: 11,
+                found: 12
+            })
+        );
+        assert_eq!(
+            FastId::from_base64("AAAA+AAAAAA").err(),
+            Some(ParseFastIdError::InvalidCharacter {
+                character: '+',
+                index: 4
+            })
+        );
+        assert_eq!(
+            FastId::from_base64("AAAAAAAAAA=").err(),
+            Some(ParseFastIdError::InvalidCharacter {
+                character: '=',
+                index: 10
+            })
+        );
+        // only the first four bits of the last character belong to the id
+        assert_eq!(
+            FastId::from_base64("AAAAAAAAAAB").err(),
+            Some(ParseFastIdError::InvalidCharacter {
+                character: 'B',
+                index: 10
+            })
+        );
+        assert_eq!(
+            FastId::from_base64("gAAAAAAAAAA").err(),
+            Some(ParseFastIdError::Overflow)
+        );
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn can_parse_legacy_base64() {
+        let id = FastIdWorker::new(1).next_id();
+
+        let parsed = FastId::from_legacy_base64(&id.to_legacy_base64()).unwrap();
+        assert_eq!(parsed.as_i64(), id.as_i64());
+
+        assert_eq!(
+            FastId::from_legacy_base64("AAAAAAAAAA=").err(),
+            Some(ParseFastIdError::InvalidLength {
+                expected: 12,
+                found: 11
+            })
+        );
+        assert_eq!(
+            FastId::from_legacy_base64("AAAA-AAAAAA=").err(),
+            Some(ParseFastIdError::InvalidCharacter {
+                character: '-',
+                index: 4
+            })
+        );
+        assert_eq!(
+            FastId::from_legacy_base64("AAAAAAAAAAAA").err(),
+            Some(ParseFastIdError::InvalidCharacter {
+                character: 'A',
+                index: 11
+            })
+        );
+        assert_eq!(
+            FastId::from_legacy_base64("//////////8=").err(),
+            Some(ParseFastIdError::Overflow)
+        );
+    }
+
+    #[test]
+    fn sortable_encodings_sort_like_ids() {
+        let worker = FastIdWorker::new(1);
+        let mut ids: Vec<FastId> = (0..100).map(|_| worker.next_id()).collect();
+        ids.push(FastId::try_from(0i64).unwrap());
+        ids.push(FastId::try_from(i64::MAX).unwrap());
+        ids.sort();
+
+        type Encoding = (
+            fn(&FastId) -> String,
+            fn(&str) -> Result<FastId, ParseFastIdError>,
+        );
+        let encodings: [Encoding; 3] = [
+            (FastId::to_base32, FastId::from_base32),
+            (FastId::to_sortable_base62, FastId::from_sortable_base62),
+            (FastId::to_sortable_base64, FastId::from_sortable_base64),
+        ];
+
+        for (encode, decode) in encodings {
+            let strings: Vec<String> = ids.iter().map(encode).collect();
+            assert!(strings.windows(2).all(|w| w[0] < w[1]));
+
+            let parsed: Vec<FastId> = strings.iter().map(|s| decode(s).unwrap()).collect();
+            assert_eq!(parsed, ids);
+        }
+
+        assert_eq!(
+            FastId::from_base32("7ZZZZZZZZZZZZ").map(|id| id.as_i64()),
+            Ok(i64::MAX)
+        );
+        assert_eq!(
+            FastId::from_base32("8000000000000").err(),
+            Some(ParseFastIdError::Overflow)
+        );
+        assert_eq!(
+            FastId::from_base32("000000000000U").err(),
+            Some(ParseFastIdError::InvalidCharacter {
+                character: 'U',
+                index: 12
+            })
+        );
+        assert_eq!(
+            FastId::from_sortable_base64("7__________").err(),
+            Some(ParseFastIdError::Overflow)
+        );
+    }
+
+    #[test]
+    fn can_parse_base32_with_check_symbol() {
+        let id = FastIdWorker::new(1).next_id();
+
+        for grouped in [false, true] {
+            let s = id.to_base32_check(grouped);
+            assert_eq!(FastId::from_base32_check(&s), Ok(id));
+            assert_eq!(FastId::from_base32_check(&s.to_lowercase()), Ok(id));
+        }
+
+        // swapping two symbols changes the check symbol
+        let id = FastId::try_from(1_234_567_890_123_456_789i64).unwrap();
+        assert_eq!(id.to_base32_check(false), "128GGYHYYK08NT");
+        assert_eq!(
+            FastId::from_base32_check("128GGYHYYK80NT"),
+            Err(ParseFastIdError::InvalidCheckSymbol {
+                expected: '7',
+                found: 'T'
+            })
+        );
+
+        assert_eq!(
+            FastId::from_base32_check("7ZZZZ-ZZZZZ-ZZZ5"),
+            Ok(FastId::try_from(i64::MAX).unwrap())
+        );
+        assert_eq!(
+            FastId::from_base32_check("80000-00000-0006"),
+            Err(ParseFastIdError::Overflow)
+        );
+    }
+
+    #[test]
+    fn ids_order_as_generated() {
+        use std::collections::HashSet;
+
+        let worker = FastIdWorker::new(1);
+        let ids: Vec<FastId> = (0..100).map(|_| worker.next_id()).collect();
+
+        let mut sorted = ids.clone();
+        sorted.reverse();
+        sorted.sort();
+        assert_eq!(sorted, ids);
+
+        let unique: HashSet<FastId> = ids.iter().copied().collect();
+        assert_eq!(unique.len(), ids.len());
+    }
+
+    #[test]
+    fn ids_are_as_small_as_integers() {
+        assert_eq!(std::mem::size_of::<FastId>(), std::mem::size_of::<i64>());
+    }
+
+    #[test]
+    fn can_convert_integers() {
+        let id = FastId::try_from(0x1234_i64).unwrap();
+        assert_eq!(i64::from(id), 0x1234);
+        assert_eq!(u64::from(id), 0x1234);
+        assert_eq!(FastId::try_from(0x1234_u64), Ok(id));
+
+        assert!(FastId::try_from(-1_i64).is_err());
+        assert!(FastId::try_from(u64::MAX).is_err());
+
+        assert_eq!(format!("{:x}", id), "1234");
+        assert_eq!(format!("{:#X}", id), "0x1234");
+        assert_eq!(format!("{:o}", id), "11064");
+    }
+
+    #[test]
+    fn ids_of_one_machine_are_contiguous_in_time_machine_sequence_order() {
+        let layout = IdLayout::default().with_order(FieldOrder::TimeMachineSequence);
+        let clock = MockClock::new(layout.timestamp_at(10));
+        let worker = FastIdWorker::builder()
+            .clock(clock)
+            .layout(layout)
+            .machine_id(3)
+            .build()
+            .unwrap();
+
+        let first = worker.next_id();
+        let second = worker.next_id();
+        assert_eq!(second.as_i64(), first.as_i64() + 1);
+
+        let parts = worker.decode(&second);
+        assert_eq!(parts.timestamp, layout.timestamp_at(10));
+        assert_eq!(parts.sequence, 1);
+        assert_eq!(parts.machine_id, 3);
+    }
+
+    #[test]
+    fn refuses_to_wrap_exhausted_time_field() {
+        static WARNED_AT: AtomicU64 = AtomicU64::new(0);
+
+        fn warn(expires_at: SystemTime) {
+            let secs = expires_at
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            WARNED_AT.store(secs, Ordering::Relaxed);
+        }
+
+        let layout = IdLayout::new(8, 16, 7)
+            .unwrap()
+            .with_tick(TickResolution::SECOND)
+            .with_epoch(0);
+        let clock = MockClock::new(layout.timestamp_at(250));
+        let worker = FastIdWorker::builder()
+            .clock(clock.clone())
+            .layout(layout)
+            .exhaustion_warning(Duration::from_secs(3), warn)
+            .build()
+            .unwrap();
+
+        assert_eq!(worker.expires_at(), Some(layout.timestamp_at(256)));
+        assert_eq!(worker.remaining_lifetime(), Some(Duration::from_secs(6)));
+
+        worker.next_id();
+        assert_eq!(WARNED_AT.load(Ordering::Relaxed), 0);
+
+        clock.set(layout.timestamp_at(252));
+        worker.next_id();
+        assert_eq!(WARNED_AT.load(Ordering::Relaxed), 256);
+
+        clock.set(layout.timestamp_at(255));
+        assert_eq!(
+            worker.decode(&worker.next_id()).timestamp,
+            layout.timestamp_at(255)
+        );
+
+        clock.set(layout.timestamp_at(256));
+        assert_eq!(worker.try_next_id(), Err(FastIdError::TimeExhausted));
+        assert_eq!(worker.remaining_lifetime(), Some(Duration::new(0, 0)));
+    }
 }
@@ -0,0 +1,252 @@
+//! Serde support for [`FastId`], enabled by the `serde` feature.
+//!
+//! By default ids are serialized as numbers. The modules below pick another
+//! wire representation for a field with `#[serde(with = "...")]`:
+//!
+//! ```
+//! # use fastid::FastId;
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Order {
+//!     // JavaScript numbers lose precision above 2^53
+//!     #[serde(with = "fastid::serde::string")]
+//!     id: FastId,
+//! }
+//! ```
+
+use std::fmt;
+
+use ::serde::de::{self, Deserializer, Visitor};
+use ::serde::ser::Serializer;
+use ::serde::{Deserialize, Serialize};
+
+use crate::{FastId, IdPrefix, TypedFastId};
+
+impl Serialize for FastId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        number::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FastId {
+    /// Accepts both the number and the decimal string representation from
+    /// human-readable formats, and the number from binary formats, which
+    /// cannot tell them apart.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(FastIdVisitor)
+        } else {
+            number::deserialize(deserializer)
+        }
+    }
+}
+
+struct FastIdVisitor;
+
+impl<'de> Visitor<'de> for FastIdVisitor {
+    type Value = FastId;
+
+    fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("a non-negative 64-bit integer or its decimal string")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        if v < 0 {
+            return Err(E::invalid_value(de::Unexpected::Signed(v), &self));
+        }
+
+        Ok(FastId::from_raw(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        i64::try_from(v)
+            .map(FastId::from_raw)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+impl<T: IdPrefix> Serialize for TypedFastId<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, T: IdPrefix> Deserialize<'de> for TypedFastId<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Serializes ids as numbers, the default representation.
+pub mod number {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(id: &FastId, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(id.as_i64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FastId, D::Error> {
+        deserializer.deserialize_i64(FastIdVisitor)
+    }
+}
+
+/// Serializes ids as decimal strings.
+pub mod string {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(id: &FastId, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(id)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FastId, D::Error> {
+        deserializer.deserialize_str(FastIdVisitor)
+    }
+}
+
+/// Serializes ids with [`FastId::to_sortable_base62`].
+pub mod base62 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(id: &FastId, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&id.to_sortable_base62())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FastId, D::Error> {
+        let s = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+
+        FastId::from_sortable_base62(&s).map_err(de::Error::custom)
+    }
+}
+
+/// Serializes ids as [`GuidFormat::FastId`](crate::GuidFormat::FastId) GUIDs
+/// of the [default layout](crate::IdLayout::default).
+///
+/// Used with `#[serde(with = "fastid::serde::guid")]`, deserializing GUIDs
+/// back to ids with [`FastId::try_from`]. Ids of other layouts or formats are
+/// serialized by functions calling [`IdLayout::to_guid`](crate::IdLayout::to_guid)
+/// and [`IdLayout::from_guid`](crate::IdLayout::from_guid).
+#[cfg(feature = "guid")]
+pub mod guid {
+    use super::*;
+    use crate::{GuidFormat, IdLayout};
+
+    pub fn serialize<S: Serializer>(id: &FastId, serializer: S) -> Result<S::Ok, S::Error> {
+        let guid = IdLayout::default()
+            .to_guid(id, GuidFormat::FastId)
+            .map_err(::serde::ser::Error::custom)?;
+
+        serializer.collect_str(&guid)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FastId, D::Error> {
+        let s = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        let guid = uuid::Uuid::parse_str(&s).map_err(de::Error::custom)?;
+
+        FastId::try_from(guid).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastIdWorker;
+
+    #[derive(Serialize, Deserialize)]
+    struct Payload {
+        id: FastId,
+        #[serde(with = "string")]
+        string: FastId,
+        #[serde(with = "base62")]
+        base62: FastId,
+        #[cfg(feature = "guid")]
+        #[serde(with = "guid")]
+        guid: FastId,
+    }
+
+    #[test]
+    fn can_round_trip_representations() {
+        let id = FastId::try_from(1_234_567_890_123_456_789i64).unwrap();
+
+        let payload = Payload {
+            id,
+            string: id,
+            base62: id,
+            #[cfg(feature = "guid")]
+            guid: id,
+        };
+
+        let json = serde_json::to_value(&payload).unwrap();
+        assert_eq!(json["id"], serde_json::json!(id.as_i64()));
+        assert_eq!(json["string"], serde_json::json!(id.to_string()));
+        assert_eq!(json["base62"], serde_json::json!(id.to_sortable_base62()));
+        #[cfg(feature = "guid")]
+        assert_eq!(
+            json["guid"],
+            serde_json::json!("4421e8fb-0022-1000-b4fb-000000008115")
+        );
+
+        let parsed: Payload = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.id, id);
+        assert_eq!(parsed.string, id);
+        assert_eq!(parsed.base62, id);
+        #[cfg(feature = "guid")]
+        assert_eq!(parsed.guid, id);
+    }
+
+    #[test]
+    fn can_round_trip_binary_formats() {
+        let id = FastId::try_from(1_234_567_890_123_456_789i64).unwrap();
+
+        let bytes = bincode::serialize(&id).unwrap();
+        assert_eq!(bytes, 1_234_567_890_123_456_789i64.to_le_bytes());
+        assert_eq!(bincode::deserialize::<FastId>(&bytes).unwrap(), id);
+
+        let payload = Payload {
+            id,
+            string: id,
+            base62: id,
+            #[cfg(feature = "guid")]
+            guid: id,
+        };
+        let bytes = bincode::serialize(&payload).unwrap();
+        let parsed: Payload = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(parsed.id, id);
+        assert_eq!(parsed.string, id);
+    }
+
+    #[test]
+    fn accepts_numbers_and_strings_by_default() {
+        let id: FastId = serde_json::from_str("\"42\"").unwrap();
+        assert_eq!(id.as_i64(), 42);
+
+        let id: FastId = serde_json::from_str("42").unwrap();
+        assert_eq!(id.as_i64(), 42);
+
+        assert!(serde_json::from_str::<FastId>("-1").is_err());
+        assert!(serde_json::from_str::<FastId>("9223372036854775808").is_err());
+    }
+
+    #[test]
+    fn can_round_trip_typed_ids() {
+        struct User;
+
+        impl IdPrefix for User {
+            const PREFIX: &'static str = "user";
+        }
+
+        let id = TypedFastId::<User>::new(FastIdWorker::new(1).next_id());
+
+        let json = serde_json::to_value(id).unwrap();
+        assert_eq!(json, serde_json::json!(id.to_string()));
+        assert_eq!(
+            serde_json::from_value::<TypedFastId<User>>(json).unwrap(),
+            id
+        );
+        assert!(serde_json::from_str::<TypedFastId<User>>("\"order_00000000001\"").is_err());
+    }
+}
@@ -0,0 +1,283 @@
+//! Generators splitting the sequence field between threads.
+
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+use crate::{Clock, FastId, FastIdError, FastIdParts, FastIdWorker, FastIdWorkerBuilder, IdLayout};
+
+/// A generator whose sequence field is split into shards, each claimed by one
+/// thread to generate ids without touching the state of other threads.
+///
+/// The top bits of the sequence of every id hold the shard it was generated
+/// by, so ids are unique across shards. Each shard gets the remaining
+/// sequence bits, and the clock regression and sequence exhaustion policies
+/// of the builder apply to every shard on its own.
+///
+/// ```
+/// use fastid::FastIdWorker;
+///
+/// let worker = FastIdWorker::builder().machine_id(1).build_sharded(2).unwrap();
+///
+/// std::thread::scope(|scope| {
+///     for _ in 0..4 {
+///         let shard = worker.claim_shard().unwrap();
+///
+///         scope.spawn(move || {
+///             for _ in 0..1000 {
+///                 let id = shard.next_id();
+///                 assert_eq!(shard.decode(&id).machine_id, 1);
+///             }
+///         });
+///     }
+/// });
+/// ```
+#[derive(Debug)]
+pub struct ShardedFastIdWorker<C> {
+    shared: Arc<Shared<C>>,
+}
+
+#[derive(Debug)]
+struct Shared<C> {
+    /// Builds the worker of a shard, with the sequence field of a shard.
+    builder: FastIdWorkerBuilder<C>,
+    layout: IdLayout,
+    shard_bits: usize,
+    shards: Mutex<Shards>,
+}
+
+/// The shards that can be claimed, allocated as they are first claimed since
+/// there may be far more shards than threads.
+#[derive(Debug)]
+struct Shards {
+    /// The lowest shard that was never claimed.
+    next: u64,
+    /// The released shards and the state their last worker ended with.
+    released: Vec<(u64, u64)>,
+}
+
+impl<C: Clock + Clone> ShardedFastIdWorker<C> {
+    /// Splits the sequence field of `layout` between `2^shard_bits` shards,
+    /// whose workers are built by `builder`.
+    pub(crate) fn new(
+        builder: FastIdWorkerBuilder<C>,
+        layout: IdLayout,
+        shard_bits: usize,
+    ) -> Self {
+        ShardedFastIdWorker {
+            shared: Arc::new(Shared {
+                builder,
+                layout,
+                shard_bits,
+                shards: Mutex::new(Shards {
+                    next: 0,
+                    released: Vec::new(),
+                }),
+            }),
+        }
+    }
+
+    pub fn layout(&self) -> &IdLayout {
+        &self.shared.layout
+    }
+
+    pub fn shards(&self) -> u64 {
+        1 << self.shared.shard_bits
+    }
+
+    /// Claims an unused shard, failing with [`FastIdError::ShardsExhausted`]
+    /// if every shard is claimed.
+    ///
+    /// The shard is released when the [`FastIdShard`] is dropped, and the
+    /// next claim of it continues after its last id.
+    pub fn claim_shard(&self) -> Result<FastIdShard<C>, FastIdError> {
+        let (shard, state) = {
+            let mut shards = self.shared.shards.lock().unwrap_or_else(|e| e.into_inner());
+
+            match shards.released.pop() {
+                Some(released) => released,
+                None if shards.next < self.shards() => {
+                    shards.next += 1;
+                    (shards.next - 1, 0)
+                }
+                None => return Err(FastIdError::ShardsExhausted),
+            }
+        };
+
+        let worker = self
+            .shared
+            .builder
+            .clone()
+            .build()
+            .expect("shard configuration was checked by build_sharded");
+        worker.state.store(state, Ordering::Relaxed);
+
+        Ok(FastIdShard {
+            shared: Arc::clone(&self.shared),
+            shard,
+            worker,
+        })
+    }
+}
+
+/// A shard of a [`ShardedFastIdWorker`], generating ids on its own.
+#[derive(Debug)]
+pub struct FastIdShard<C: Clock + Clone> {
+    shared: Arc<Shared<C>>,
+    shard: u64,
+    worker: FastIdWorker<C>,
+}
+
+impl<C: Clock + Clone> FastIdShard<C> {
+    pub fn shard(&self) -> u64 {
+        self.shard
+    }
+
+    pub fn layout(&self) -> &IdLayout {
+        &self.shared.layout
+    }
+
+    /// See [`FastIdWorker::decode`].
+    pub fn decode(&self, id: &FastId) -> FastIdParts {
+        self.shared.layout.decode(id)
+    }
+
+    /// Generates the next id of this shard.
+    ///
+    /// # Panics
+    ///
+    /// Panics where [`FastIdWorker::next_id`] does.
+    pub fn next_id(&self) -> FastId {
+        self.try_next_id()
+            .unwrap_or_else(|e| panic!("failed to generate id: {}", e))
+    }
+
+    /// Generates the next id of this shard, failing where
+    /// [`FastIdWorker::try_next_id`] does.
+    pub fn try_next_id(&self) -> Result<FastId, FastIdError> {
+        let id = self.worker.try_next_id()?;
+        let (ts, sequence, machine_id) = self.worker.layout().unpack(id.as_u64());
+
+        let sequence = (self.shard << self.worker.layout().sequence_bits()) | sequence;
+
+        Ok(FastId::from_raw(
+            self.shared.layout.pack(ts, sequence, machine_id) as i64,
+        ))
+    }
+}
+
+impl<C: Clock + Clone> Drop for FastIdShard<C> {
+    fn drop(&mut self) {
+        let state = self.worker.state.load(Ordering::Relaxed);
+
+        self.shared
+            .shards
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .released
+            .push((self.shard, state));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::assert_unique_across_threads;
+    use crate::{MockClock, SequenceExhaustion};
+
+    #[test]
+    fn shards_generate_unique_ids() {
+        let worker = FastIdWorker::builder()
+            .machine_id(3)
+            .build_sharded(2)
+            .unwrap();
+
+        assert_unique_across_threads(|| {
+            let shard = worker.claim_shard().unwrap();
+
+            move || (0..1000).map(|_| shard.next_id()).collect()
+        });
+    }
+
+    #[test]
+    fn shards_own_the_top_sequence_bits() {
+        let clock = MockClock::new(IdLayout::default().timestamp_at(10));
+        let worker = FastIdWorker::builder()
+            .clock(clock.clone())
+            .sequence_exhaustion(SequenceExhaustion::Fail)
+            .build_sharded(3)
+            .unwrap();
+        assert_eq!(worker.shards(), 8);
+
+        let shard = worker.claim_shard().unwrap();
+        let per_shard = (worker.layout().max_sequence() + 1) / 8;
+
+        let ids: Vec<_> = (0..per_shard).map(|_| shard.next_id()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        for (i, id) in ids.iter().enumerate() {
+            let parts = shard.decode(id);
+            assert_eq!(parts.sequence, shard.shard() * per_shard + i as u64);
+            assert_eq!(parts.timestamp, worker.layout().timestamp_at(10));
+        }
+
+        // each shard runs out of sequence numbers on its own
+        assert!(matches!(
+            shard.try_next_id(),
+            Err(FastIdError::WouldBlock { .. })
+        ));
+        let other = worker.claim_shard().unwrap();
+        assert!(other.try_next_id().is_ok());
+    }
+
+    #[test]
+    fn released_shards_continue_after_their_last_id() {
+        let clock = MockClock::new(IdLayout::default().timestamp_at(10));
+        let worker = FastIdWorker::builder()
+            .clock(clock)
+            .build_sharded(1)
+            .unwrap();
+
+        let first = worker.claim_shard().unwrap();
+        let second = worker.claim_shard().unwrap();
+        assert_eq!(
+            worker.claim_shard().err(),
+            Some(FastIdError::ShardsExhausted)
+        );
+
+        let last = first.next_id();
+        let shard = first.shard();
+        drop(first);
+
+        let reclaimed = worker.claim_shard().unwrap();
+        assert_eq!(reclaimed.shard(), shard);
+        assert!(reclaimed.next_id() > last);
+        assert_ne!(second.shard(), shard);
+    }
+
+    #[test]
+    fn allocates_shards_as_they_are_claimed() {
+        let layout = IdLayout::new(18, 0, 45).unwrap();
+        let worker = FastIdWorker::builder()
+            .clock(MockClock::new(layout.timestamp_at(10)))
+            .layout(layout)
+            .build_sharded(40)
+            .unwrap();
+        assert_eq!(worker.shards(), 1 << 40);
+
+        let first = worker.claim_shard().unwrap();
+        let second = worker.claim_shard().unwrap();
+        assert_eq!((first.shard(), second.shard()), (0, 1));
+        assert_ne!(first.next_id(), second.next_id());
+    }
+
+    #[test]
+    fn rejects_more_shards_than_sequence_bits() {
+        let result = FastIdWorker::builder()
+            .layout(IdLayout::new(40, 16, 4).unwrap())
+            .build_sharded(5);
+
+        assert_eq!(
+            result.err(),
+            Some(FastIdError::TooManyShards { bits: 5, max: 4 })
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! Ids tagged with the type of entity they identify.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use crate::encoding::Alphabet;
+use crate::{FastId, ParseFastIdError};
+
+/// How a [`TypedFastId`] is written after its prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PrefixStyle {
+    /// The prefix, an underscore and the 11 characters of
+    /// [`FastId::to_sortable_base62`], like the ids of Stripe.
+    #[default]
+    Base62,
+    /// A [TypeID](https://github.com/jetify-com/typeid/tree/main/spec): the
+    /// prefix, an underscore and 26 lower case characters of Crockford's
+    /// base32 holding the id as the low bits of a 128-bit UUID.
+    ///
+    /// The prefix is at most 63 lower case ASCII letters and underscores, and
+    /// neither starts nor ends with an underscore. An empty prefix drops the
+    /// underscore.
+    TypeId,
+}
+
+impl PrefixStyle {
+    /// Returns whether `prefix` can be written in this style.
+    const fn accepts(&self, prefix: &str) -> bool {
+        let bytes = prefix.as_bytes();
+
+        match self {
+            PrefixStyle::Base62 => !bytes.is_empty(),
+            PrefixStyle::TypeId => {
+                if bytes.len() > 63 {
+                    return false;
+                }
+                if !bytes.is_empty() && (bytes[0] == b'_' || bytes[bytes.len() - 1] == b'_') {
+                    return false;
+                }
+
+                let mut i = 0;
+                while i < bytes.len() {
+                    if !bytes[i].is_ascii_lowercase() && bytes[i] != b'_' {
+                        return false;
+                    }
+                    i += 1;
+                }
+
+                true
+            }
+        }
+    }
+}
+
+/// The type of entity a [`TypedFastId`] identifies.
+///
+/// ```
+/// use fastid::{IdPrefix, PrefixStyle};
+///
+/// struct User;
+///
+/// impl IdPrefix for User {
+///     const PREFIX: &'static str = "user";
+///     const STYLE: PrefixStyle = PrefixStyle::TypeId;
+/// }
+/// ```
+pub trait IdPrefix {
+    /// The prefix identifying the type, checked against the rules of
+    /// [`IdPrefix::STYLE`] when the id is first formatted or parsed.
+    const PREFIX: &'static str;
+
+    /// How the id is written after the prefix, [`PrefixStyle::Base62`] by
+    /// default.
+    const STYLE: PrefixStyle = PrefixStyle::Base62;
+}
+
+/// A [`FastId`] with a prefix naming the type of entity it identifies, so an
+/// order id cannot be passed where a user id is expected.
+///
+/// ```
+/// use fastid::{FastIdWorker, IdPrefix, TypedFastId};
+///
+/// struct Customer;
+///
+/// impl IdPrefix for Customer {
+///     const PREFIX: &'static str = "cus";
+/// }
+///
+/// let id: TypedFastId<Customer> = TypedFastId::new(FastIdWorker::new(1).next_id());
+/// let s = id.to_string();
+/// assert!(s.starts_with("cus_"));
+/// assert_eq!(s.parse(), Ok(id));
+/// ```
+pub struct TypedFastId<T> {
+    id: FastId,
+    prefix: PhantomData<fn() -> T>,
+}
+
+impl<T: IdPrefix> TypedFastId<T> {
+    /// Fails to compile for prefixes the style does not accept.
+    const VALID: () = assert!(
+        T::STYLE.accepts(T::PREFIX),
+        "prefix not allowed by its style"
+    );
+
+    pub fn new(id: FastId) -> Self {
+        TypedFastId {
+            id,
+            prefix: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> FastId {
+        self.id
+    }
+}
+
+impl<T: IdPrefix> From<FastId> for TypedFastId<T> {
+    fn from(id: FastId) -> Self {
+        TypedFastId::new(id)
+    }
+}
+
+impl<T> From<TypedFastId<T>> for FastId {
+    fn from(id: TypedFastId<T>) -> Self {
+        id.id
+    }
+}
+
+impl<T: IdPrefix> fmt::Display for TypedFastId<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let () = Self::VALID;
+
+        match T::STYLE {
+            PrefixStyle::Base62 => {
+                write!(fmt, "{}_{}", T::PREFIX, self.id.to_sortable_base62())
+            }
+            PrefixStyle::TypeId if T::PREFIX.is_empty() => {
+                fmt.write_str(&Alphabet::TYPEID.encode(self.id.as_u64()))
+            }
+            PrefixStyle::TypeId => write!(
+                fmt,
+                "{}_{}",
+                T::PREFIX,
+                Alphabet::TYPEID.encode(self.id.as_u64())
+            ),
+        }
+    }
+}
+
+impl<T: IdPrefix> FromStr for TypedFastId<T> {
+    type Err = ParseFastIdError;
+
+    /// Parses the form produced by [`Display`](fmt::Display), rejecting ids
+    /// with another prefix.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let () = Self::VALID;
+
+        let (offset, suffix) = if T::STYLE == PrefixStyle::TypeId && T::PREFIX.is_empty() {
+            (0, s)
+        } else {
+            let suffix = s
+                .strip_prefix(T::PREFIX)
+                .and_then(|rest| rest.strip_prefix('_'))
+                .ok_or(ParseFastIdError::InvalidPrefix {
+                    expected: T::PREFIX,
+                })?;
+
+            (T::PREFIX.len() + 1, suffix)
+        };
+
+        let id = match T::STYLE {
+            PrefixStyle::Base62 => FastId::from_sortable_base62(suffix),
+            PrefixStyle::TypeId => FastId::from_encoding(&Alphabet::TYPEID, suffix),
+        };
+
+        // report positions in the whole string rather than in the suffix
+        id.map(TypedFastId::new).map_err(|e| match e {
+            ParseFastIdError::InvalidLength { expected, found } => {
+                ParseFastIdError::InvalidLength {
+                    expected: expected + offset,
+                    found: found + offset,
+                }
+            }
+            ParseFastIdError::InvalidCharacter { character, index } => {
+                ParseFastIdError::InvalidCharacter {
+                    character,
+                    index: index + offset,
+                }
+            }
+            e => e,
+        })
+    }
+}
+
+// implemented by hand so that `T` needs none of the traits
+
+impl<T> Clone for TypedFastId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedFastId<T> {}
+
+impl<T> PartialEq for TypedFastId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for TypedFastId<T> {}
+
+impl<T> PartialOrd for TypedFastId<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for TypedFastId<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl<T> Hash for TypedFastId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state)
+    }
+}
+
+impl<T: IdPrefix> fmt::Debug for TypedFastId<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_tuple("TypedFastId")
+            .field(&format_args!("{}", self))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastIdWorker;
+
+    struct User;
+
+    impl IdPrefix for User {
+        const PREFIX: &'static str = "user";
+    }
+
+    struct Order;
+
+    impl IdPrefix for Order {
+        const PREFIX: &'static str = "order_line";
+        const STYLE: PrefixStyle = PrefixStyle::TypeId;
+    }
+
+    struct Anonymous;
+
+    impl IdPrefix for Anonymous {
+        const PREFIX: &'static str = "";
+        const STYLE: PrefixStyle = PrefixStyle::TypeId;
+    }
+
+    #[test]
+    fn can_round_trip_prefixed_ids() {
+        let worker = FastIdWorker::new(1);
+        let first = worker.next_id();
+        let second = worker.next_id();
+
+        let user = TypedFastId::<User>::new(first);
+        assert_eq!(
+            user.to_string(),
+            format!("user_{}", first.to_sortable_base62())
+        );
+        assert_eq!(user.to_string().parse(), Ok(user));
+
+        let order = TypedFastId::<Order>::new(first);
+        assert_eq!(order.to_string().len(), "order_line_".len() + 26);
+        assert_eq!(order.to_string().parse(), Ok(order));
+        assert!(order.to_string() < TypedFastId::<Order>::new(second).to_string());
+
+        let anonymous = TypedFastId::<Anonymous>::new(first);
+        assert_eq!(anonymous.to_string().len(), 26);
+        assert_eq!(anonymous.to_string().parse(), Ok(anonymous));
+    }
+
+    #[test]
+    fn matches_the_typeid_spec() {
+        // from the valid.yml test vectors of the spec, with UUIDs below 2^63
+        let id = FastId::try_from(1i64).unwrap();
+        assert_eq!(
+            TypedFastId::<Anonymous>::new(id).to_string(),
+            "00000000000000000000000001"
+        );
+
+        let id = FastId::try_from(10i64).unwrap();
+        assert_eq!(
+            TypedFastId::<Anonymous>::new(id).to_string(),
+            "0000000000000000000000000a"
+        );
+
+        // the spec only allows lower case suffixes
+        assert_eq!(
+            "0000000000000000000000000A".parse::<TypedFastId<Anonymous>>(),
+            Err(ParseFastIdError::InvalidCharacter {
+                character: 'A',
+                index: 25
+            })
+        );
+        assert_eq!(
+            "00000000000000000000000001".parse::<TypedFastId<Anonymous>>(),
+            Ok(TypedFastId::new(FastId::try_from(1i64).unwrap()))
+        );
+        assert_eq!(
+            "8zzzzzzzzzzzzzzzzzzzzzzzzz".parse::<TypedFastId<Anonymous>>(),
+            Err(ParseFastIdError::Overflow)
+        );
+    }
+
+    #[test]
+    fn rejects_other_prefixes() {
+        let id = TypedFastId::<User>::new(FastIdWorker::new(1).next_id());
+        let order = id.to_string().replacen("user", "order", 1);
+
+        assert_eq!(
+            order.parse::<TypedFastId<User>>(),
+            Err(ParseFastIdError::InvalidPrefix { expected: "user" })
+        );
+        assert_eq!(
+            "user_0000000001".parse::<TypedFastId<User>>(),
+            Err(ParseFastIdError::InvalidLength {
+                expected: 16,
+                found: 15
+            })
+        );
+    }
+
+    #[test]
+    fn checks_typeid_prefixes() {
+        assert!(PrefixStyle::TypeId.accepts("order_line"));
+        assert!(PrefixStyle::TypeId.accepts(""));
+        assert!(!PrefixStyle::TypeId.accepts("_order"));
+        assert!(!PrefixStyle::TypeId.accepts("order_"));
+        assert!(!PrefixStyle::TypeId.accepts("Order"));
+        assert!(!PrefixStyle::TypeId.accepts("order1"));
+        assert!(!PrefixStyle::TypeId.accepts(&"a".repeat(64)));
+        assert!(!PrefixStyle::Base62.accepts(""));
+    }
+}